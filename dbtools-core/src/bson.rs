@@ -0,0 +1,672 @@
+use chrono::{NaiveDateTime, Timelike};
+use rustc_serialize::hex::{FromHex, ToHex};
+use rustc_serialize::json::{Json, JsonEvent, Parser, StackElement};
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use error::Error;
+use util::{file_stem, Input};
+use value::{Number, Record, Value};
+use extjson;
+
+/// Largest document MongoDB stores.
+const MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// Largest decimal128 coefficient: 34 nines.
+const MAX_COEFFICIENT: u128 = 9_999_999_999_999_999_999_999_999_999_999_999;
+
+/// True if an input holds BSON documents rather than JSON. Each document
+/// starts with its length as a 32-bit little endian integer, then the type of
+/// its first field, while JSON text starts with characters which, read as a
+/// length, are far beyond the largest document.
+pub fn is_bson(input: &Input) -> Result<bool, Error> {
+    let start = input.start(5)?;
+    if start.len() < 5 {
+        return Ok(false);
+    }
+    let size = u32::from_le_bytes([start[0], start[1], start[2], start[3]]) as usize;
+    Ok((5..=MAX_SIZE).contains(&size) && (start[4] == 0 || element_type(start[4])))
+}
+
+fn element_type(t: u8) -> bool {
+    matches!(t, 0x01..=0x13 | 0x7F | 0xFF)
+}
+
+fn date(ms: i64) -> Option<NaiveDateTime> {
+    NaiveDateTime::from_timestamp_opt(ms.div_euclid(1000), ms.rem_euclid(1000) as u32 * 1_000_000)
+}
+
+fn millis(d: &NaiveDateTime) -> i64 {
+    d.timestamp() * 1000 + (d.nanosecond() / 1_000_000) as i64
+}
+
+/// A decimal128 as text, as MongoDB writes it, e.g. `1.50`, `-0.001` or
+/// `1.23E+40`. NaN and the infinities, which decimal text cannot hold, are
+/// doubles.
+fn decimal_number(low: u64, high: u64) -> Number {
+    let negative = high >> 63 == 1;
+    match (high >> 58) & 0x1F {
+        0x1F => return Number::Double(f64::NAN),
+        0x1E => return Number::Double(if negative { f64::NEG_INFINITY } else { f64::INFINITY }),
+        _ => (),
+    }
+    let (exponent, coefficient) = if (high >> 61) & 3 == 3 {
+        // Only used for coefficients too large to be valid, which are 0.
+        ((high >> 47) & 0x3FFF, 0)
+    }
+    else {
+        ((high >> 49) & 0x3FFF, ((high & 0x1_FFFF_FFFF_FFFF) as u128) << 64 | low as u128)
+    };
+    let coefficient = if coefficient > MAX_COEFFICIENT { 0 } else { coefficient };
+    let exponent = exponent as i64 - 6176;
+    let digits = coefficient.to_string();
+    let adjusted = exponent + digits.len() as i64 - 1;
+    let text = if exponent == 0 {
+        digits
+    }
+    else if exponent < 0 && adjusted >= -6 {
+        let point = digits.len() as i64 + exponent;
+        if point > 0 {
+            format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+        }
+        else {
+            format!("0.{}{}", "0".repeat(-point as usize), digits)
+        }
+    }
+    else {
+        let fraction = if digits.len() > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}E{}{}", &digits[..1], fraction, if adjusted < 0 { "" } else { "+" }, adjusted)
+    };
+    Number::Decimal128(format!("{}{}", if negative { "-" } else { "" }, text))
+}
+
+/// The decimal128 of decimal text, as its low and high 64 bits, if it has
+/// no more than 34 significant digits and its exponent is in range.
+fn decimal128(text: &str) -> Option<(u64, u64)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(t) => (true, t),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (mantissa, mut exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (int, fraction) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let digits = format!("{}{}", int, fraction);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    exponent -= fraction.len() as i64;
+    let mut digits = digits.trim_start_matches('0').to_string();
+    while digits.len() > 34 && digits.ends_with('0') {
+        digits.pop();
+        exponent += 1;
+    }
+    if digits.len() > 34 || !(-6176..=6111).contains(&exponent) {
+        return None;
+    }
+    let coefficient = if digits.is_empty() { 0 } else { digits.parse::<u128>().ok()? };
+    let high = (negative as u64) << 63 | ((exponent + 6176) as u64) << 49 | (coefficient >> 64) as u64;
+    Some((coefficient as u64, high))
+}
+
+/// Reads the fields of BSON documents from a buffer.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if n > self.bytes.len() - self.pos {
+            return Err(format!("Field at byte {} runs past the end of the document", self.pos));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        let b = self.take(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    fn length(&mut self) -> Result<usize, String> {
+        let pos = self.pos;
+        usize::try_from(self.i32()?).map_err(|_| format!("Negative length at byte {}", pos))
+    }
+
+    fn utf8(&self, bytes: &[u8]) -> Result<String, String> {
+        String::from_utf8(bytes.to_vec()).map_err(|e| format!("Invalid UTF-8 before byte {}: {}", self.pos, e))
+    }
+
+    fn cstring(&mut self) -> Result<String, String> {
+        let rest = &self.bytes[self.pos..];
+        let end = rest.iter().position(|b| *b == 0).ok_or_else(|| "Unterminated name".to_string())?;
+        let bytes = self.take(end + 1)?;
+        self.utf8(&bytes[..end])
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.length()?;
+        let bytes = self.take(length)?;
+        match bytes.split_last() {
+            Some((0, text)) => self.utf8(text),
+            _ => Err(format!("Unterminated string before byte {}", self.pos)),
+        }
+    }
+
+    /// The fields of a document, or the elements of an array, in order.
+    fn elements(&mut self) -> Result<Vec<(String, Value)>, String> {
+        let (start, length) = (self.pos, self.length()?);
+        if length < 5 || length > self.bytes.len() - start {
+            return Err(format!("Invalid document length {} at byte {}", length, start));
+        }
+        let mut elements = Vec::new();
+        loop {
+            let t = self.u8()?;
+            if t == 0 {
+                break;
+            }
+            let name = self.cstring()?;
+            let value = self.value(t)?;
+            elements.push((name, value));
+        }
+        if self.pos != start + length {
+            return Err(format!("Document at byte {} does not end at its length of {}", start, length));
+        }
+        Ok(elements)
+    }
+
+    fn document(&mut self) -> Result<Record, String> {
+        let mut r = Record::new();
+        for (k, v) in self.elements()? {
+            r.insert(&k, v);
+        }
+        Ok(r)
+    }
+
+    fn value(&mut self, t: u8) -> Result<Value, String> {
+        let pos = self.pos;
+        Ok(match t {
+            0x01 => Value::Number(Number::Double(f64::from_bits(self.u64()?))),
+            // JavaScript code and symbols are kept as their text.
+            0x02 | 0x0D | 0x0E => Value::String(self.string()?),
+            0x03 => Value::Document(self.document()?),
+            0x04 => Value::Array(self.elements()?.into_iter().map(|e| e.1).collect()),
+            0x05 => {
+                let length = self.length()?;
+                let subtype = self.u8()?;
+                let mut bytes = self.take(length)?;
+                // The old binary subtype repeats the length inside the data.
+                if subtype == 2 && bytes.len() >= 4 {
+                    bytes = &bytes[4..];
+                }
+                Value::Binary(subtype, bytes.to_vec())
+            },
+            0x06 | 0x0A => Value::Null,
+            0x07 => Value::ObjectId(self.take(12)?.to_hex()),
+            0x08 => Value::Bool(self.u8()? != 0),
+            0x09 => {
+                let ms = self.u64()? as i64;
+                Value::Date(date(ms).ok_or_else(|| format!("Date out of range at byte {}", pos))?)
+            },
+            0x0B => {
+                let pattern = self.cstring()?;
+                Value::Regex(pattern, self.cstring()?)
+            },
+            // A DBPointer, written as the DBRef which replaced it.
+            0x0C => {
+                let mut r = Record::new();
+                r.insert("$ref", Value::String(self.string()?));
+                r.insert("$id", Value::ObjectId(self.take(12)?.to_hex()));
+                Value::Document(r)
+            },
+            // JavaScript code with its scope, kept as the code.
+            0x0F => {
+                self.length()?;
+                let code = self.string()?;
+                self.document()?;
+                Value::String(code)
+            },
+            0x10 => Value::Number(Number::Int32(self.i32()?)),
+            0x11 => {
+                let ts = self.u64()?;
+                Value::Timestamp((ts >> 32) as u32, ts as u32)
+            },
+            0x12 => Value::Number(Number::Int64(self.u64()? as i64)),
+            0x13 => {
+                let low = self.u64()?;
+                Value::Number(decimal_number(low, self.u64()?))
+            },
+            0xFF => Value::MinKey,
+            0x7F => Value::MaxKey,
+            _ => return Err(format!("Unknown field type 0x{:02x} at byte {}", t, pos - 1)),
+        })
+    }
+}
+
+/// Decode a BSON document, keeping the type of every value.
+pub fn decode_document(bytes: &[u8]) -> Result<Record, String> {
+    let mut d = Decoder { bytes, pos: 0 };
+    let r = d.document()?;
+    if d.pos != bytes.len() {
+        return Err(format!("Document ends at byte {} of {}", d.pos, bytes.len()));
+    }
+    Ok(r)
+}
+
+fn cstring(out: &mut Vec<u8>, s: &str) {
+    // Names cannot hold a NUL, which ends them.
+    out.extend(s.bytes().filter(|b| *b != 0));
+    out.push(0);
+}
+
+fn string(out: &mut Vec<u8>, s: &str) {
+    out.extend(&(s.len() as i32 + 1).to_le_bytes());
+    out.extend(s.as_bytes());
+    out.push(0);
+}
+
+fn number(out: &mut Vec<u8>, key: &str, n: &Number) {
+    let double = |out: &mut Vec<u8>, d: f64| {
+        out.push(0x01);
+        cstring(out, key);
+        out.extend(&d.to_le_bytes());
+    };
+    match *n {
+        Number::Int(i) if i32::try_from(i).is_ok() => {
+            out.push(0x10);
+            cstring(out, key);
+            out.extend(&(i as i32).to_le_bytes());
+        },
+        Number::Int32(i) => {
+            out.push(0x10);
+            cstring(out, key);
+            out.extend(&i.to_le_bytes());
+        },
+        Number::Int(i) | Number::Int64(i) => {
+            out.push(0x12);
+            cstring(out, key);
+            out.extend(&i.to_le_bytes());
+        },
+        Number::Double(d) => double(out, d),
+        // Decimals a double holds stay doubles, as in JSON output.
+        Number::Decimal(ref d) if n.fits_double() => double(out, d.parse::<f64>().unwrap_or(0.0)),
+        Number::Decimal(ref d) | Number::Decimal128(ref d) => {
+            match decimal128(d) {
+                Some((low, high)) => {
+                    out.push(0x13);
+                    cstring(out, key);
+                    out.extend(&low.to_le_bytes());
+                    out.extend(&high.to_le_bytes());
+                },
+                None => double(out, n.to_f64()),
+            }
+        },
+    }
+}
+
+fn element(out: &mut Vec<u8>, key: &str, v: &Value) {
+    let head = |out: &mut Vec<u8>, t: u8| {
+        out.push(t);
+        cstring(out, key);
+    };
+    match *v {
+        Value::ObjectId(ref oid) => {
+            match oid.from_hex() {
+                Ok(ref bytes) if bytes.len() == 12 => {
+                    head(out, 0x07);
+                    out.extend(bytes);
+                },
+                _ => {
+                    head(out, 0x02);
+                    string(out, oid);
+                },
+            }
+        },
+        Value::Date(ref d) => {
+            head(out, 0x09);
+            out.extend(&millis(d).to_le_bytes());
+        },
+        Value::Number(ref n) => number(out, key, n),
+        Value::Bool(b) => {
+            head(out, 0x08);
+            out.push(b as u8);
+        },
+        Value::String(ref s) => {
+            head(out, 0x02);
+            string(out, s);
+        },
+        Value::Null => head(out, 0x0A),
+        Value::Document(ref r) => {
+            head(out, 0x03);
+            document(out, r.iter().map(|f| (f.0.clone(), &f.1)));
+        },
+        Value::Array(ref a) => {
+            head(out, 0x04);
+            document(out, a.iter().enumerate().map(|(i, v)| (i.to_string(), v)));
+        },
+        Value::Binary(subtype, ref bytes) => {
+            head(out, 0x05);
+            // The old binary subtype has its length again, inside the data.
+            if subtype == 2 {
+                out.extend(&(bytes.len() as i32 + 4).to_le_bytes());
+                out.push(subtype);
+                out.extend(&(bytes.len() as i32).to_le_bytes());
+            }
+            else {
+                out.extend(&(bytes.len() as i32).to_le_bytes());
+                out.push(subtype);
+            }
+            out.extend(bytes);
+        },
+        Value::Regex(ref pattern, ref options) => {
+            head(out, 0x0B);
+            cstring(out, pattern);
+            cstring(out, options);
+        },
+        Value::Timestamp(t, i) => {
+            head(out, 0x11);
+            out.extend(&((t as u64) << 32 | i as u64).to_le_bytes());
+        },
+        Value::MinKey => head(out, 0xFF),
+        Value::MaxKey => head(out, 0x7F),
+    }
+}
+
+fn document<'a, I: Iterator<Item = (String, &'a Value)>>(out: &mut Vec<u8>, fields: I) {
+    let start = out.len();
+    out.extend(&[0; 4]);
+    for (k, v) in fields {
+        element(out, &k, v);
+    }
+    out.push(0);
+    let length = (out.len() - start) as i32;
+    out[start..start + 4].copy_from_slice(&length.to_le_bytes());
+}
+
+/// Encode a record as a BSON document, each value as its BSON type.
+pub fn encode_document(r: &Record) -> Vec<u8> {
+    let mut out = Vec::new();
+    document(&mut out, r.iter().map(|f| (f.0.clone(), &f.1)));
+    out
+}
+
+/// Reads a BSON dump, e.g. a collection written by `mongodump`: documents one
+/// after another, each starting with its length.
+pub struct BsonReader {
+    input: String,
+    reader: BufReader<Box<dyn Read>>,
+    /// Position of the next document, and how many have been read.
+    offset: u64,
+    count: u64,
+    done: bool,
+}
+
+impl BsonReader {
+    pub fn open(input: &Input) -> Result<BsonReader, Error> {
+        let reader = BufReader::new(input.reader()?);
+        Ok(BsonReader { input: input.name().to_string(), reader, offset: 0, count: 0, done: false })
+    }
+
+    /// Read into `buf` until it is full or the input ends.
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            match self.reader.read(&mut buf[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        Ok(read)
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let message = format!("Document {} at byte {}: {}", self.count + 1, self.offset, message);
+        Error::parse(0, 0, &message).in_file(&self.input)
+    }
+
+    fn read(&mut self) -> Result<Option<Record>, Error> {
+        let mut length = [0; 4];
+        match self.fill(&mut length).map_err(|e| Error::io(&self.input, e))? {
+            0 => return Ok(None),
+            4 => (),
+            _ => return Err(self.error("Truncated length")),
+        }
+        let size = i32::from_le_bytes(length);
+        if size < 5 || size as usize > MAX_SIZE {
+            return Err(self.error(&format!("Invalid document length {}", size)));
+        }
+        let mut bytes = vec![0; size as usize];
+        bytes[..4].copy_from_slice(&length);
+        if self.fill(&mut bytes[4..]).map_err(|e| Error::io(&self.input, e))? != bytes.len() - 4 {
+            return Err(self.error(&format!("Truncated document of {} bytes", size)));
+        }
+        // A bad document can be skipped, as its length says where the next
+        // one starts.
+        let r = decode_document(&bytes).map_err(|e| self.error(&e).in_record(&bytes));
+        self.offset += size as u64;
+        self.count += 1;
+        r.map(Some)
+    }
+}
+
+impl Iterator for BsonReader {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Result<Record, Error>> {
+        if self.done {
+            return None;
+        }
+        match self.read() {
+            Ok(Some(r)) => Some(Ok(r)),
+            Ok(None) => None,
+            Err(e) => {
+                self.done = !matches!(e, Error::BadRecord { .. });
+                Some(Err(e))
+            },
+        }
+    }
+}
+
+/// An index of a collection, e.g. `{"v":2,"key":{"email":1},"name":"email_1","unique":true}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index {
+    pub name: String,
+    /// Fields and their directions, e.g. 1, -1 or "text", in order.
+    pub key: Vec<(String, Value)>,
+    pub unique: bool,
+}
+
+/// The `*.metadata.json` beside each collection of a `mongodump` directory,
+/// which `mongorestore` reads to recreate the collection and its indexes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub collection: String,
+    pub indexes: Vec<Index>,
+}
+
+/// Names of the fields of each index key, in the order written, which a
+/// JSON object does not keep.
+fn key_order(text: &str) -> Vec<Vec<String>> {
+    let mut keys: Vec<Vec<String>> = Vec::new();
+    let mut parser = Parser::new(text.chars());
+    while let Some(event) = parser.next() {
+        match event {
+            JsonEvent::Error(_) => break,
+            JsonEvent::ObjectEnd | JsonEvent::ArrayEnd => continue,
+            _ => (),
+        }
+        let stack = parser.stack();
+        if stack.len() != 4 {
+            continue;
+        }
+        if let (StackElement::Key("indexes"), StackElement::Index(i), StackElement::Key("key"), StackElement::Key(k))
+            = (stack.get(0), stack.get(1), stack.get(2), stack.get(3)) {
+            let i = i as usize;
+            while keys.len() <= i {
+                keys.push(Vec::new());
+            }
+            keys[i].push(k.to_string());
+        }
+    }
+    keys
+}
+
+impl Metadata {
+    /// Metadata of a collection with only the index on `_id`, which every
+    /// collection has.
+    pub fn new(collection: &str) -> Metadata {
+        Metadata {
+            collection: collection.to_string(),
+            indexes: vec![Index {
+                name: "_id_".to_string(),
+                key: vec![("_id".to_string(), Value::Number(Number::Int(1)))],
+                unique: false,
+            }],
+        }
+    }
+
+    /// The metadata file of a dump file, e.g. `dump/shop/people.metadata.json`
+    /// for `dump/shop/people.bson`.
+    pub fn file(dump: &str) -> String {
+        let name = format!("{}.metadata.json", file_stem(dump));
+        Path::new(dump).with_file_name(name).to_string_lossy().into_owned()
+    }
+
+    pub fn read(path: &str) -> Result<Metadata, Error> {
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let json = Json::from_str(&text).map_err(|e| Error::parse(0, 0, &e.to_string()).in_file(path))?;
+        let keys = key_order(&text);
+        let mut metadata = Metadata {
+            collection: json.find("collectionName").and_then(|c| c.as_string()).unwrap_or("").to_string(),
+            indexes: Vec::new(),
+        };
+        let indexes = json.find("indexes").and_then(|i| i.as_array()).map_or(&[][..], |i| &i[..]);
+        for (i, index) in indexes.iter().enumerate() {
+            let key = match index.find("key").and_then(|k| k.as_object()) {
+                Some(key) => key,
+                None => continue,
+            };
+            let order = keys.get(i).map_or(&[][..], |k| &k[..]);
+            metadata.indexes.push(Index {
+                name: index.find("name").and_then(|n| n.as_string()).unwrap_or("").to_string(),
+                key: order.iter().filter_map(|k| key.get(k).and_then(|d| extjson::to_value(d).ok()).map(|d| (k.clone(), d))).collect(),
+                unique: index.find("unique").and_then(|u| u.as_boolean()).unwrap_or(false),
+            });
+        }
+        Ok(metadata)
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        let indexes = self.indexes.iter()
+            .map(|index| {
+                let mut key = Record::new();
+                for (k, d) in &index.key {
+                    key.insert(k, d.clone());
+                }
+                let mut r = Record::new();
+                r.insert("v", Value::Number(Number::Int(2)));
+                r.insert("key", Value::Document(key));
+                r.insert("name", Value::String(index.name.clone()));
+                if index.unique {
+                    r.insert("unique", Value::Bool(true));
+                }
+                Value::Document(r)
+            })
+            .collect();
+        let mut r = Record::new();
+        r.insert("options", Value::Document(Record::new()));
+        r.insert("indexes", Value::Array(indexes));
+        r.insert("collectionName", Value::String(self.collection.clone()));
+        r.insert("type", Value::String("collection".to_string()));
+        fs::write(path, format!("{}\n", extjson::format_document(&r, false, true))).map_err(|e| Error::io(path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(text: &str) -> Option<Number> {
+        decimal128(text).map(|(low, high)| decimal_number(low, high))
+    }
+
+    fn decimal(text: &str) -> Option<Number> {
+        Some(Number::Decimal128(text.to_string()))
+    }
+
+    #[test]
+    fn decimal128_round_trips() {
+        for text in &["0", "1.50", "-0.001", "123456789", "0.000001", "1.23E+42", "-1E-7", "1E+3",
+            "9999999999999999999999999999999999", "1E-6176", "9.999999999999999999999999999999999E+6144"] {
+            assert_eq!(round_trip(text), decimal(text));
+        }
+    }
+
+    #[test]
+    fn decimal128_text_is_normalised() {
+        assert_eq!(round_trip("+1.0"), decimal("1.0"));
+        assert_eq!(round_trip("1e3"), decimal("1E+3"));
+        assert_eq!(round_trip("0.0000001"), decimal("1E-7"));
+        assert_eq!(round_trip("00012"), decimal("12"));
+        // Trailing zeros past 34 digits are folded into the exponent.
+        assert_eq!(round_trip(&format!("1{}", "0".repeat(40))), decimal("1.000000000000000000000000000000000E+40"));
+    }
+
+    #[test]
+    fn decimal128_out_of_range() {
+        assert_eq!(decimal128("12345678901234567890123456789012345"), None);
+        assert_eq!(decimal128("1E+6112"), None);
+        assert_eq!(decimal128("1E-6177"), None);
+        assert_eq!(decimal128("1.2.3"), None);
+        assert_eq!(decimal128(""), None);
+        assert_eq!(decimal128("NaN"), None);
+    }
+
+    #[test]
+    fn decimal128_special_values() {
+        assert!(matches!(decimal_number(0, 0x7C00_0000_0000_0000), Number::Double(d) if d.is_nan()));
+        assert_eq!(decimal_number(0, 0x7800_0000_0000_0000), Number::Double(f64::INFINITY));
+        assert_eq!(decimal_number(0, 0xF800_0000_0000_0000), Number::Double(f64::NEG_INFINITY));
+        // Known encodings, as MongoDB writes them.
+        assert_eq!(decimal128("1"), Some((1, 0x3040_0000_0000_0000)));
+        assert_eq!(decimal128("-1.50"), Some((150, 0xB03C_0000_0000_0000)));
+    }
+
+    #[test]
+    fn documents_round_trip() {
+        let mut r = Record::new();
+        r.insert("_id", Value::ObjectId("5f1d7f3e8e4b2a3c4d5e6f70".to_string()));
+        r.insert("price", Value::Number(Number::Decimal128("1.50".to_string())));
+        r.insert("huge", Value::Number(Number::Decimal128("1.23E+42".to_string())));
+        r.insert("count", Value::Number(Number::Int32(7)));
+        r.insert("tags", Value::Array(vec![Value::String("a".to_string()), Value::Null]));
+        assert_eq!(decode_document(&encode_document(&r)), Ok(r));
+    }
+    #[test]
+    fn old_binary_keeps_its_inner_length() {
+        let mut r = Record::new();
+        r.insert("old", Value::Binary(2, vec![1, 2, 3]));
+        r.insert("new", Value::Binary(0, vec![1, 2, 3]));
+        let bytes = encode_document(&r);
+        // Total, then the old binary's element: type, key, length, subtype, inner length, data.
+        assert_eq!(&bytes[4..20], &[0x05, b'o', b'l', b'd', 0, 7, 0, 0, 0, 2, 3, 0, 0, 0, 1, 2]);
+        assert_eq!(decode_document(&bytes), Ok(r));
+    }
+}
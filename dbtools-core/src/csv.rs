@@ -0,0 +1,405 @@
+use csvlib::{self, ErrorKind, Position, ReaderBuilder, StringRecordsIntoIter, Trim};
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::fs::File;
+use error::Error;
+use value::{FieldSet, Record, Value};
+use util::{self, format_timestamp, Input};
+use flatten::Flatten;
+use infer::Inference;
+
+/// Where in the input an error of the csv crate happened.
+fn csv_error(input: &str, e: &csvlib::Error) -> Error {
+    let line = |pos: &Option<Position>| pos.as_ref().map_or(0, |p| p.line());
+    let error = match *e.kind() {
+        ErrorKind::Io(ref e) => return Error::io(input, io::Error::new(e.kind(), e.to_string())),
+        ErrorKind::Utf8 { ref pos, ref err } => {
+            Error::parse(line(pos), 0, &format!("Invalid UTF-8 in field {}", err.field() + 1))
+        },
+        ErrorKind::UnequalLengths { ref pos, expected_len, len } => {
+            Error::schema("", line(pos), &format!("Row has {} fields but the header has {}", len, expected_len))
+        },
+        _ => Error::parse(0, 0, &e.to_string()),
+    };
+    error.in_file(input)
+}
+
+/// A row which cannot be read, though the rows after it can.
+fn bad_row(e: &csvlib::Error) -> bool {
+    matches!(*e.kind(), ErrorKind::Utf8 { .. } | ErrorKind::UnequalLengths { .. })
+}
+
+/// A character option which has to be a single byte, e.g. a separator.
+pub fn single_byte(option: &str, value: &str) -> Result<u8, Error> {
+    match *value.as_bytes() {
+        [b] => Ok(b),
+        _ => Err(Error::usage(&format!("{} '{}' is not a single character", option, value))),
+    }
+}
+
+/// How a CSV file to read is written. Quoted fields may span lines.
+#[derive(Clone, Debug)]
+pub struct CsvDialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    /// Character escaping a quote in a quoted field, besides doubling it.
+    pub escape: Option<u8>,
+    /// Lines starting with this character are skipped.
+    pub comment: Option<u8>,
+    /// Trim whitespace around headers and fields.
+    pub trim: bool,
+    /// The first line names the columns.
+    pub header: bool,
+    /// Names for the columns, used in place of the header line. Without
+    /// either, columns are named `column1`, `column2` and so on.
+    pub columns: Vec<String>,
+}
+
+impl Default for CsvDialect {
+    fn default() -> CsvDialect {
+        CsvDialect {
+            delimiter: b',',
+            quote: b'"',
+            escape: None,
+            comment: None,
+            trim: false,
+            header: true,
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl CsvDialect {
+    fn reader(&self, f: File) -> csvlib::Reader<File> {
+        ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .escape(self.escape)
+            .comment(self.comment)
+            .trim(if self.trim { Trim::All } else { Trim::None })
+            .has_headers(self.header)
+            .from_reader(f)
+    }
+}
+
+/// Reads a CSV file into records. The file is read twice: first to infer
+/// the type of each column from all of its values, then to convert each
+/// value to that type.
+pub struct CsvReader {
+    input: String,
+    path: String,
+    dialect: CsvDialect,
+    headers: Vec<String>,
+    inference: Inference,
+    records: StringRecordsIntoIter<File>,
+}
+
+impl CsvReader {
+    pub fn open(input: &Input, dialect: &CsvDialect) -> Result<CsvReader, Error> {
+        let mut r = dialect.reader(input.file()?);
+        // Without a header line, this is the first row, which is not skipped.
+        let first = r.headers().map_err(|e| csv_error(input.name(), &e))?.clone();
+        let headers: Vec<String> = if !dialect.columns.is_empty() {
+            if dialect.columns.len() != first.len() && !first.is_empty() {
+                let message = format!("{} column names given but the file has {} columns",
+                    dialect.columns.len(), first.len());
+                return Err(Error::schema(input.name(), 1, &message));
+            }
+            dialect.columns.clone()
+        }
+        else if dialect.header {
+            first.iter().map(|h| h.to_string()).collect()
+        }
+        else {
+            (1..=first.len()).map(|i| format!("column{}", i)).collect()
+        };
+
+        let mut inference = Inference::new(&headers);
+        for row in r.records() {
+            let row = match row {
+                Ok(row) => row,
+                // Bad rows are left to be reported when the records are read.
+                Err(ref e) if bad_row(e) => continue,
+                Err(e) => return Err(csv_error(input.name(), &e)),
+            };
+            let line = row.position().map_or(0, |p| p.line());
+            inference.add(line, row.iter());
+        }
+
+        let mut reader = CsvReader {
+            input: input.name().to_string(),
+            path: input.path().to_string(),
+            dialect: dialect.clone(),
+            headers,
+            inference,
+            records: r.into_records(),
+        };
+        reader.rewind()?;
+        Ok(reader)
+    }
+
+    /// Start reading the records again from the first, keeping the
+    /// inferred column types.
+    pub fn rewind(&mut self) -> Result<(), Error> {
+        let f = File::open(&self.path).map_err(|e| Error::io(&self.input, e))?;
+        self.records = self.dialect.reader(f).into_records();
+        Ok(())
+    }
+
+    /// The bytes of a row which could not be read, from its start to where
+    /// the reader is now.
+    fn raw_row(&self, e: &csvlib::Error) -> Vec<u8> {
+        let start = e.position().map_or(0, |p| p.byte());
+        let end = self.records.reader().position().byte();
+        let mut raw = vec![0; end.saturating_sub(start) as usize];
+        let read = File::open(&self.path).and_then(|mut f| {
+            f.seek(SeekFrom::Start(start))?;
+            f.read_exact(&mut raw)
+        });
+        if read.is_err() {
+            raw.clear();
+        }
+        while raw.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+            raw.pop();
+        }
+        raw
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Inferred column types and the values which widened them.
+    pub fn inference(&self) -> &Inference {
+        &self.inference
+    }
+}
+
+impl Iterator for CsvReader {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Result<Record, Error>> {
+        let r = match self.records.next()? {
+            Ok(r) => r,
+            Err(ref e) if bad_row(e) => {
+                let raw = self.raw_row(e);
+                return Some(Err(csv_error(&self.input, e).in_record(&raw)));
+            },
+            Err(e) => return Some(Err(csv_error(&self.input, &e))),
+        };
+        let mut record = Record::new();
+        for ((h, t), f) in self.headers.iter().zip(&self.inference.types).zip(r.iter()) {
+            record.insert(h, t.convert(f));
+        }
+        Some(Ok(record))
+    }
+}
+
+/// Which fields are quoted in CSV output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quoting {
+    /// Only fields containing the separator, a quote or a line break.
+    Minimal,
+    All,
+    /// Every field but numbers and missing values.
+    NonNumeric,
+}
+
+impl Quoting {
+    pub fn parse(quoting: &str) -> Option<Quoting> {
+        match quoting {
+            "minimal" => Some(Quoting::Minimal),
+            "all" => Some(Quoting::All),
+            "non-numeric" => Some(Quoting::NonNumeric),
+            _ => None,
+        }
+    }
+}
+
+/// How a quote inside a quoted field is written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Escape {
+    /// Doubled, as in RFC 4180: `"say ""hi"""`.
+    Double,
+    /// After a backslash, with backslashes doubled: `"say \"hi\""`.
+    Backslash,
+}
+
+impl Escape {
+    pub fn parse(escape: &str) -> Option<Escape> {
+        match escape {
+            "double" => Some(Escape::Double),
+            "backslash" => Some(Escape::Backslash),
+            _ => None,
+        }
+    }
+}
+
+/// Writes records as a CSV file with a header line naming every field found
+/// in any record; missing fields are left empty. Embedded documents and
+/// arrays are flattened into dotted columns. Fields are quoted as RFC 4180
+/// describes unless other quoting is chosen.
+pub struct CsvWriter {
+    pub separator: String,
+    pub quote: char,
+    pub escape: Escape,
+    pub quoting: Quoting,
+    /// End lines with CRLF rather than LF.
+    pub crlf: bool,
+    /// Start the file with a UTF-8 byte order mark, for Excel.
+    pub bom: bool,
+    /// Use "Z" as timezone for timestamps rather than +0000.
+    pub tz: bool,
+    /// Write ObjectIds and booleans with their MongoDB type annotations.
+    pub mongo_types: bool,
+    pub flatten: Flatten,
+}
+
+impl Default for CsvWriter {
+    fn default() -> CsvWriter {
+        CsvWriter {
+            separator: ",".to_string(),
+            quote: '"',
+            escape: Escape::Double,
+            quoting: Quoting::Minimal,
+            crlf: false,
+            bom: false,
+            tz: false,
+            mongo_types: false,
+            flatten: Flatten::default(),
+        }
+    }
+}
+
+impl CsvWriter {
+    /// A field as written, quoted if `quote` or if it has to be.
+    fn field(&self, text: &str, quote: bool) -> String {
+        let backslash = self.escape == Escape::Backslash;
+        let special = text.contains(self.separator.as_str()) || text.contains(self.quote)
+            || text.contains('\n') || text.contains('\r') || (backslash && text.contains('\\'));
+        if !quote && !special {
+            return text.to_string();
+        }
+        let mut field = String::with_capacity(text.len() + 2);
+        field.push(self.quote);
+        for c in text.chars() {
+            if c == self.quote {
+                field.push(if backslash { '\\' } else { self.quote });
+            }
+            else if c == '\\' && backslash {
+                field.push('\\');
+            }
+            field.push(c);
+        }
+        field.push(self.quote);
+        field
+    }
+
+    /// A value as written, or an empty field if it is missing.
+    fn value_field(&self, v: Option<&Value>) -> String {
+        let quote = match (self.quoting, v) {
+            (Quoting::All, _) => true,
+            (Quoting::NonNumeric, Some(&Value::Null)) | (Quoting::NonNumeric, None) => false,
+            (Quoting::NonNumeric, Some(&Value::Number(_))) => false,
+            (Quoting::NonNumeric, _) => true,
+            (Quoting::Minimal, _) => false,
+        };
+        self.field(&v.map_or(String::new(), |v| self.format_value(v)), quote)
+    }
+
+    fn line_end(&self) -> &str {
+        if self.crlf { "\r\n" } else { "\n" }
+    }
+
+    fn format_value(&self, v: &Value) -> String {
+        match *v {
+            Value::ObjectId(ref oid) => {
+                if self.mongo_types {
+                    format!("ObjectId({})", oid)
+                }
+                else {
+                    oid.clone()
+                }
+            },
+            Value::Date(ref d) => format_timestamp(d, self.tz),
+            Value::Number(ref n) => n.to_string(),
+            Value::Bool(b) => {
+                if self.mongo_types {
+                    format!("%!s(bool={})", b)
+                }
+                else {
+                    b.to_string()
+                }
+            },
+            Value::String(ref s) => s.clone(),
+            Value::Binary(subtype, ref bytes) => {
+                if self.mongo_types {
+                    format!("BinData({},{})", subtype, bytes.to_base64(STANDARD))
+                }
+                else {
+                    bytes.to_base64(STANDARD)
+                }
+            },
+            Value::Regex(ref pattern, ref options) => format!("/{}/{}", pattern, options),
+            Value::Timestamp(t, i) => ((t as u64) << 32 | i as u64).to_string(),
+            Value::MinKey => "MinKey".to_string(),
+            Value::MaxKey => "MaxKey".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Add the flattened fields of a record to the columns.
+    pub fn add_columns(&self, columns: &mut FieldSet, r: &Record) {
+        columns.add(&self.flatten.record(r, self.tz));
+    }
+
+    /// Columns for the records: every flattened field, in the order first seen.
+    pub fn columns<I: IntoIterator<Item = Result<Record, Error>>>(&self, records: I) -> Result<Vec<String>, Error> {
+        let mut columns = FieldSet::new();
+        for r in records {
+            self.add_columns(&mut columns, &r?);
+        }
+        Ok(columns.into_fields())
+    }
+
+    /// Start a CSV file with a header line of the columns.
+    pub fn create(&self, columns: Vec<String>, output: &str) -> Result<CsvOutput<'_>, Error> {
+        let f = util::create(output)?;
+        let mut out = CsvOutput { writer: self, path: output.to_string(), columns, out: BufWriter::new(f) };
+        let header: Vec<String> = out.columns.iter().map(|c| self.field(c, self.quoting != Quoting::Minimal)).collect();
+        let bom = if self.bom { "\u{feff}" } else { "" };
+        write!(out.out, "{}{}{}", bom, header.join(&self.separator), self.line_end()).map_err(|e| Error::io(output, e))?;
+        Ok(out)
+    }
+
+    /// Write a CSV file of the records with the given columns.
+    pub fn write<I: IntoIterator<Item = Result<Record, Error>>>(&self, columns: Vec<String>, records: I,
+    output: &str) -> Result<(), Error> {
+        let mut out = self.create(columns, output)?;
+        for r in records {
+            out.write(&r?)?;
+        }
+        out.finish()
+    }
+}
+
+/// A CSV file being written, one line per record.
+pub struct CsvOutput<'a> {
+    writer: &'a CsvWriter,
+    path: String,
+    columns: Vec<String>,
+    out: BufWriter<Box<dyn Write>>,
+}
+
+impl<'a> CsvOutput<'a> {
+    pub fn write(&mut self, r: &Record) -> Result<(), Error> {
+        let r = self.writer.flatten.record(r, self.writer.tz);
+        let w = self.writer;
+        let row: Vec<String> = self.columns.iter().map(|c| w.value_field(r.get(c))).collect();
+        write!(self.out, "{}{}", row.join(&w.separator), w.line_end()).map_err(|e| Error::io(&self.path, e))
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.out.flush().map_err(|e| Error::io(&self.path, e))
+    }
+}
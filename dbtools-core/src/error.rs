@@ -0,0 +1,118 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Why a conversion failed. Each kind of error has its own exit code:
+///
+/// | Code | Error                                        |
+/// |------|----------------------------------------------|
+/// | 1    | `Io`: a file could not be opened, read or written |
+/// | 2    | `Usage`: bad command line options            |
+/// | 3    | `Parse`: malformed input                     |
+/// | 4    | `Schema`: input which does not fit its table |
+#[derive(Debug)]
+pub enum Error {
+    Io { path: String, error: io::Error },
+    Usage(String),
+    /// Malformed input at a line and column of a file, counted from 1;
+    /// 0 if not known.
+    Parse { file: String, line: u64, column: u64, message: String },
+    /// Well-formed input of the wrong shape, e.g. an INSERT naming an
+    /// unknown column or a CSV row with too many fields.
+    Schema { file: String, line: u64, message: String },
+    /// A parse or schema error in one record, with the bytes of the record
+    /// as they appear in the input. Reading can carry on with the next one.
+    BadRecord { error: Box<Error>, raw: Vec<u8> },
+}
+
+impl Error {
+    pub fn io(path: &str, error: io::Error) -> Error {
+        Error::Io { path: path.to_string(), error }
+    }
+
+    pub fn usage(message: &str) -> Error {
+        Error::Usage(message.to_string())
+    }
+
+    /// A parse error whose file is filled in later by `in_file`.
+    pub fn parse(line: u64, column: u64, message: &str) -> Error {
+        Error::Parse { file: String::new(), line, column, message: message.to_string() }
+    }
+
+    pub fn schema(file: &str, line: u64, message: &str) -> Error {
+        Error::Schema { file: file.to_string(), line, message: message.to_string() }
+    }
+
+    /// Mark an error as affecting only the record read as `raw`.
+    pub fn in_record(self, raw: &[u8]) -> Error {
+        match self {
+            Error::BadRecord { .. } => self,
+            e => Error::BadRecord { error: Box::new(e), raw: raw.to_vec() },
+        }
+    }
+
+    /// Name the file of a parse or schema error.
+    pub fn in_file(self, name: &str) -> Error {
+        match self {
+            Error::Parse { line, column, message, .. } => {
+                Error::Parse { file: name.to_string(), line, column, message }
+            },
+            Error::Schema { line, message, .. } => Error::Schema { file: name.to_string(), line, message },
+            Error::BadRecord { error, raw } => Error::BadRecord { error: Box::new(error.in_file(name)), raw },
+            e => e,
+        }
+    }
+
+    /// What went wrong, without the file and position.
+    pub fn message(&self) -> String {
+        match *self {
+            Error::Io { ref error, .. } => error.to_string(),
+            Error::Usage(ref message) | Error::Parse { ref message, .. } | Error::Schema { ref message, .. } => {
+                message.clone()
+            },
+            Error::BadRecord { ref error, .. } => error.message(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Io { .. } => 1,
+            Error::Usage(_) => 2,
+            Error::Parse { .. } => 3,
+            Error::Schema { .. } => 4,
+            Error::BadRecord { ref error, .. } => error.exit_code(),
+        }
+    }
+}
+
+/// `file:line:column`, leaving out what is not known.
+fn position(f: &mut fmt::Formatter, file: &str, line: u64, column: u64) -> fmt::Result {
+    write!(f, "{}", file)?;
+    if line > 0 {
+        write!(f, ":{}", line)?;
+        if column > 0 {
+            write!(f, ":{}", column)?;
+        }
+    }
+    write!(f, ": ")
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io { ref path, ref error } => write!(f, "{}: {}", path, error),
+            Error::Usage(ref message) => write!(f, "{}", message),
+            Error::Parse { ref file, line, column, ref message } => {
+                position(f, file, line, column)?;
+                write!(f, "{}", message)
+            },
+            Error::Schema { ref file, line, ref message } => {
+                position(f, file, line, 0)?;
+                write!(f, "{}", message)
+            },
+            Error::BadRecord { ref error, .. } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,435 @@
+use chrono::NaiveDateTime;
+use rustc_serialize::base64::{FromBase64, ToBase64, STANDARD};
+use rustc_serialize::hex::FromHex;
+use rustc_serialize::json::{Json, JsonEvent, Object, Parser, StackElement};
+use std::convert::TryFrom;
+use value::{is_decimal_text, Number, Record, Value};
+use util::{format_timestamp, parse_date};
+
+fn from_millis(ms: i64) -> Option<NaiveDateTime> {
+    let secs = ms.div_euclid(1000);
+    let nanos = ms.rem_euclid(1000) as u32 * 1_000_000;
+    NaiveDateTime::from_timestamp_opt(secs, nanos)
+}
+
+fn number(wrapper: &str, v: &Json) -> Option<Number> {
+    let s = v.as_string()?;
+    match wrapper {
+        "$numberInt" => s.parse::<i32>().ok().map(Number::Int32),
+        "$numberLong" => s.parse::<i64>().ok().map(Number::Int64),
+        // Also accepts "Infinity", "-Infinity" and "NaN".
+        "$numberDouble" => s.parse::<f64>().ok().map(Number::Double),
+        _ => None,
+    }
+}
+
+/// The number of a `$numberDecimal` wrapper: decimal text, or NaN or an
+/// infinity, which decimal128 also holds.
+fn decimal(v: &Json) -> Result<Number, String> {
+    let text = match v.as_string() {
+        Some(s) if is_decimal_text(s) => s.to_string(),
+        Some(s) if s.eq_ignore_ascii_case("NaN") => "NaN".to_string(),
+        Some(s) if ["inf", "infinity", "+inf", "+infinity"].contains(&s.to_lowercase().as_str()) => "Infinity".to_string(),
+        Some(s) if ["-inf", "-infinity"].contains(&s.to_lowercase().as_str()) => "-Infinity".to_string(),
+        _ => return Err(format!("Invalid $numberDecimal {}", v)),
+    };
+    Ok(Number::Decimal128(text))
+}
+
+/// The date of a `$date` wrapper: ISO-8601 text or milliseconds since the
+/// epoch, which may be outside the dates chrono holds.
+fn date(v: &Json) -> Result<NaiveDateTime, String> {
+    let ms = match *v {
+        Json::String(ref s) => return parse_date(s).ok_or_else(|| format!("Invalid $date '{}'", s)),
+        Json::I64(ms) => Some(ms),
+        Json::U64(ms) => i64::try_from(ms).ok(),
+        Json::F64(ms) => Some(ms as i64),
+        Json::Object(ref o) => o.get("$numberLong").and_then(|n| n.as_string()).and_then(|n| n.parse::<i64>().ok()),
+        _ => return Err(format!("Invalid $date {}", v)),
+    };
+    ms.and_then(from_millis).ok_or_else(|| format!("$date {} is out of range", v))
+}
+
+fn binary(data: &Json, subtype: &Json) -> Option<Value> {
+    let bytes = data.as_string()?.from_base64().ok()?;
+    let subtype = match *subtype {
+        Json::String(ref s) => u8::from_str_radix(s, 16).ok()?,
+        Json::I64(n) => n as u8,
+        Json::U64(n) => n as u8,
+        _ => return None,
+    };
+    Some(Value::Binary(subtype, bytes))
+}
+
+/// Recognise an Extended JSON type wrapper such as `{"$oid": ...}`.
+fn wrapper(o: &Object) -> Option<Value> {
+    if o.len() == 2 {
+        // Legacy forms of $binary and $regex.
+        if let (Some(b), Some(t)) = (o.get("$binary"), o.get("$type")) {
+            return binary(b, t);
+        }
+        if let (Some(p), Some(f)) = (o.get("$regex"), o.get("$options")) {
+            return Some(Value::Regex(p.as_string()?.to_string(), f.as_string()?.to_string()));
+        }
+        return None;
+    }
+    if o.len() != 1 {
+        return None;
+    }
+
+    let (k, v) = o.iter().next()?;
+    match k.as_ref() {
+        "$oid" => Some(Value::ObjectId(v.as_string()?.to_string())),
+        "$numberInt" | "$numberLong" | "$numberDouble" => number(k, v).map(Value::Number),
+        "$binary" => {
+            let b = v.as_object()?;
+            binary(b.get("base64")?, b.get("subType")?)
+        },
+        "$uuid" => {
+            let bytes = v.as_string()?.replace("-", "").from_hex().ok()?;
+            Some(Value::Binary(4, bytes))
+        },
+        "$regularExpression" => {
+            let r = v.as_object()?;
+            let pattern = r.get("pattern")?.as_string()?.to_string();
+            let options = r.get("options")?.as_string()?.to_string();
+            Some(Value::Regex(pattern, options))
+        },
+        "$timestamp" => {
+            let ts = v.as_object()?;
+            Some(Value::Timestamp(ts.get("t")?.as_u64()? as u32, ts.get("i")?.as_u64()? as u32))
+        },
+        "$minKey" => Some(Value::MinKey),
+        "$maxKey" => Some(Value::MaxKey),
+        _ => None,
+    }
+}
+
+/// Names of the fields of each object of a JSON text, in the order written,
+/// which a JSON object does not keep.
+#[derive(Default)]
+struct FieldOrder {
+    fields: Vec<(String, FieldOrder)>,
+    items: Vec<FieldOrder>,
+}
+
+static NO_ORDER: FieldOrder = FieldOrder { fields: Vec::new(), items: Vec::new() };
+
+impl FieldOrder {
+    fn read(text: &str) -> FieldOrder {
+        // Each object or array being read, and whether it is an item of the
+        // one it is in rather than a field.
+        let mut stack: Vec<(FieldOrder, bool)> = Vec::new();
+        let mut parser = Parser::new(text.chars());
+        while let Some(event) = parser.next() {
+            match event {
+                JsonEvent::Error(_) => break,
+                JsonEvent::ObjectEnd | JsonEvent::ArrayEnd => {
+                    // The outermost one is kept as the order of the text.
+                    if stack.len() > 1 {
+                        let (order, item) = stack.pop().unwrap_or_default();
+                        let top = stack.len() - 1;
+                        let parent = &mut stack[top].0;
+                        let last = if item { parent.items.last_mut() } else { parent.fields.last_mut().map(|f| &mut f.1) };
+                        if let Some(last) = last {
+                            *last = order;
+                        }
+                    }
+                    continue;
+                },
+                _ => (),
+            }
+            let mut item = false;
+            if let Some(parent) = stack.last_mut() {
+                match parser.stack().top() {
+                    Some(StackElement::Key(k)) => parent.0.fields.push((k.to_string(), FieldOrder::default())),
+                    Some(StackElement::Index(_)) => {
+                        parent.0.items.push(FieldOrder::default());
+                        item = true;
+                    },
+                    None => (),
+                }
+            }
+            if let JsonEvent::ObjectStart | JsonEvent::ArrayStart = event {
+                stack.push((FieldOrder::default(), item));
+            }
+        }
+        stack.into_iter().next().map_or_else(FieldOrder::default, |s| s.0)
+    }
+}
+
+/// Convert a MongoDB Extended JSON tree into a typed value. A `$date` which
+/// is not a date, or a `$numberDecimal` which is not a number, is an error.
+pub fn to_value(v: &Json) -> Result<Value, String> {
+    ordered_value(v, &NO_ORDER)
+}
+
+/// Convert the tree parsed from `text` into a typed value, as `to_value`
+/// does, keeping the fields of documents in the order written.
+pub fn to_value_in_order(v: &Json, text: &str) -> Result<Value, String> {
+    ordered_value(v, &FieldOrder::read(text))
+}
+
+fn ordered_value(v: &Json, order: &FieldOrder) -> Result<Value, String> {
+    Ok(match *v {
+        Json::Object(ref o) => {
+            if let (1, Some(d)) = (o.len(), o.get("$date")) {
+                return date(d).map(Value::Date);
+            }
+            if let (1, Some(d)) = (o.len(), o.get("$numberDecimal")) {
+                return decimal(d).map(Value::Number);
+            }
+            if let Some(v) = wrapper(o) {
+                return Ok(v);
+            }
+            let mut r = Record::new();
+            for (k, order) in &order.fields {
+                if let Some(v) = o.get(k) {
+                    if !r.contains_key(k) {
+                        r.insert(k, ordered_value(v, order)?);
+                    }
+                }
+            }
+            for (k, v) in o.iter() {
+                if !r.contains_key(k) {
+                    r.insert(k, ordered_value(v, &NO_ORDER)?);
+                }
+            }
+            Value::Document(r)
+        },
+        Json::Array(ref a) => {
+            let items = a.iter().enumerate().map(|(i, v)| ordered_value(v, order.items.get(i).unwrap_or(&NO_ORDER)));
+            Value::Array(items.collect::<Result<_, _>>()?)
+        },
+        // Only $date wrappers are dates; text which looks like one is kept.
+        Json::String(ref s) => Value::String(s.clone()),
+        Json::I64(n) => Value::Number(Number::Int(n)),
+        Json::U64(n) => {
+            match i64::try_from(n) {
+                Ok(n) => Value::Number(Number::Int(n)),
+                Err(_) => Value::Number(Number::Decimal(n.to_string())),
+            }
+        },
+        Json::F64(n) => Value::Number(Number::Double(n)),
+        Json::Boolean(b) => Value::Bool(b),
+        Json::Null => Value::Null,
+    })
+}
+
+fn string(s: &str) -> String {
+    Json::String(s.to_string()).to_string()
+}
+
+/// Numbers JSON cannot hold, such as NaN, are written as a `$numberDouble`
+/// or `$numberDecimal` wrapper, or as a string without `mongo_types`. So are
+/// decimals with more digits than a double holds, and numbers of a declared
+/// type.
+fn format_number(n: &Number, mongo_types: bool) -> String {
+    match *n {
+        Number::Int(_) => n.to_string(),
+        Number::Int32(_) | Number::Int64(_) | Number::Decimal128(_) if mongo_types => {
+            let wrapper = match *n {
+                Number::Int32(_) => "$numberInt",
+                Number::Int64(_) => "$numberLong",
+                _ => "$numberDecimal",
+            };
+            format!("{{\"{}\":{}}}", wrapper, string(&n.to_string()))
+        },
+        Number::Int32(_) | Number::Int64(_) => n.to_string(),
+        Number::Double(d) if d.is_finite() => n.to_string(),
+        Number::Double(_) => {
+            if mongo_types {
+                format!("{{\"$numberDouble\":{}}}", string(&n.to_string()))
+            }
+            else {
+                string(&n.to_string())
+            }
+        },
+        Number::Decimal(ref d) | Number::Decimal128(ref d) => {
+            if mongo_types && !n.fits_double() {
+                format!("{{\"$numberDecimal\":{}}}", string(d))
+            }
+            else if d.parse::<f64>().is_ok_and(f64::is_finite) {
+                d.clone()
+            }
+            else {
+                string(d)
+            }
+        },
+    }
+}
+
+/// Write a typed value as MongoDB Extended JSON. Without `mongo_types`,
+/// ObjectIds, dates and binary data are written as plain strings.
+pub fn format(v: &Value, tz: bool, mongo_types: bool) -> String {
+    match *v {
+        Value::ObjectId(ref oid) => {
+            if mongo_types {
+                format!("{{\"$oid\":{}}}", string(oid))
+            }
+            else {
+                string(oid)
+            }
+        },
+        Value::Date(ref d) => {
+            let d = string(&format_timestamp(d, tz));
+            if mongo_types {
+                format!("{{\"$date\":{}}}", d)
+            }
+            else {
+                d
+            }
+        },
+        Value::Number(ref n) => format_number(n, mongo_types),
+        Value::Bool(b) => b.to_string(),
+        Value::String(ref s) => string(s),
+        Value::Null => "null".to_string(),
+        Value::Document(ref r) => format_document(r, tz, mongo_types),
+        Value::Array(ref a) => {
+            let a: Vec<String> = a.iter().map(|v| format(v, tz, mongo_types)).collect();
+            format!("[{}]", a.join(","))
+        },
+        Value::Binary(subtype, ref bytes) => {
+            if mongo_types {
+                format!("{{\"$binary\":{{\"base64\":\"{}\",\"subType\":\"{:02x}\"}}}}", bytes.to_base64(STANDARD), subtype)
+            }
+            else {
+                string(&bytes.to_base64(STANDARD))
+            }
+        },
+        Value::Regex(ref pattern, ref options) => {
+            if mongo_types {
+                format!("{{\"$regularExpression\":{{\"pattern\":{},\"options\":{}}}}}", string(pattern), string(options))
+            }
+            else {
+                string(&format!("/{}/{}", pattern, options))
+            }
+        },
+        Value::Timestamp(t, i) => {
+            if mongo_types {
+                format!("{{\"$timestamp\":{{\"t\":{},\"i\":{}}}}}", t, i)
+            }
+            else {
+                ((t as u64) << 32 | i as u64).to_string()
+            }
+        },
+        Value::MinKey => "{\"$minKey\":1}".to_string(),
+        Value::MaxKey => "{\"$maxKey\":1}".to_string(),
+    }
+}
+
+pub fn format_document(r: &Record, tz: bool, mongo_types: bool) -> String {
+    let ff: Vec<String> = r.iter()
+        .map(|(k, v)| format!("{}:{}", string(k), format(v, tz, mongo_types)))
+        .collect();
+    format!("{{{}}}", ff.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn value(text: &str) -> Result<Value, String> {
+        to_value(&Json::from_str(text).unwrap())
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, m, d).and_hms(0, 0, 0)
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(value(r#"{"$date":"2020-01-02T00:00:00Z"}"#), Ok(Value::Date(date(2020, 1, 2))));
+        assert_eq!(value(r#"{"$date":86400000}"#), Ok(Value::Date(date(1970, 1, 2))));
+        assert_eq!(value(r#"{"$date":-1}"#), Ok(Value::Date(date(1970, 1, 1) - chrono::Duration::milliseconds(1))));
+        assert_eq!(value(r#"{"$date":{"$numberLong":"0"}}"#), Ok(Value::Date(date(1970, 1, 1))));
+    }
+
+    #[test]
+    fn bad_dates_are_errors() {
+        assert!(value(r#"{"$date":"yesterday"}"#).is_err());
+        assert!(value(r#"{"$date":true}"#).is_err());
+        assert!(value(r#"{"$date":9223372036854775807}"#).is_err());
+        assert!(value(r#"{"$date":{"$numberLong":"-9223372036854775808"}}"#).is_err());
+        assert!(value(r#"{"$date":18446744073709551615}"#).is_err());
+    }
+
+    #[test]
+    fn date_strings_stay_strings() {
+        assert_eq!(value(r#""2020-01-02""#), Ok(Value::String("2020-01-02".to_string())));
+        assert_eq!(value(r#""2020-01-02T00:00:00Z""#), Ok(Value::String("2020-01-02T00:00:00Z".to_string())));
+    }
+
+    #[test]
+    fn wrappers() {
+        assert_eq!(value(r#"{"$oid":"5f1d7f3e8e4b2a3c4d5e6f70"}"#), Ok(Value::ObjectId("5f1d7f3e8e4b2a3c4d5e6f70".to_string())));
+        assert_eq!(value(r#"{"$binary":{"base64":"eHl6","subType":"00"}}"#), Ok(Value::Binary(0, b"xyz".to_vec())));
+        assert_eq!(value(r#"{"$binary":"eHl6","$type":"80"}"#), Ok(Value::Binary(0x80, b"xyz".to_vec())));
+        assert_eq!(value(r#"{"$timestamp":{"t":1600000000,"i":7}}"#), Ok(Value::Timestamp(1600000000, 7)));
+        assert_eq!(value(r#"{"$minKey":1}"#), Ok(Value::MinKey));
+    }
+
+    #[test]
+    fn formatted_values_read_back() {
+        let values = vec![
+            Value::Date(date(1969, 12, 31)),
+            Value::Number(Number::Int64(i64::MAX)),
+            Value::Number(Number::Decimal128("-0.001".to_string())),
+            Value::Binary(4, vec![0; 16]),
+            Value::Regex("^a.*".to_string(), "i".to_string()),
+            Value::String("tab\tquote\"".to_string()),
+        ];
+        for v in values {
+            assert_eq!(value(&format(&v, false, true)), Ok(v));
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(value(r#"{"$numberLong":"-9223372036854775808"}"#), Ok(Value::Number(Number::Int64(i64::MIN))));
+        assert_eq!(value(r#"{"$numberInt":"2147483647"}"#), Ok(Value::Number(Number::Int32(i32::MAX))));
+        assert_eq!(value(r#"{"$numberDecimal":"1.50"}"#), Ok(Value::Number(Number::Decimal128("1.50".to_string()))));
+        assert_eq!(value("18446744073709551615"), Ok(Value::Number(Number::Decimal("18446744073709551615".to_string()))));
+        match value(r#"{"$numberDouble":"NaN"}"#) {
+            Ok(Value::Number(Number::Double(d))) => assert!(d.is_nan()),
+            v => panic!("{:?}", v),
+        }
+        // A wrapper out of range for its type is kept as a document.
+        assert!(matches!(value(r#"{"$numberInt":"2147483648"}"#), Ok(Value::Document(_))));
+    }
+
+    #[test]
+    fn fields_keep_their_order() {
+        let text = r#"{"z":1,"a":{"y":[{"q":1,"c":2}],"b":null},"m":"x"}"#;
+        let r = match to_value_in_order(&Json::from_str(text).unwrap(), text) {
+            Ok(Value::Document(r)) => r,
+            v => panic!("{:?}", v),
+        };
+        assert_eq!(r.keys(), vec!["z", "a", "m"]);
+        match r.get("a") {
+            Some(Value::Document(a)) => {
+                assert_eq!(a.keys(), vec!["y", "b"]);
+                match a.get("y") {
+                    Some(Value::Array(items)) => match items[0] {
+                        Value::Document(ref item) => assert_eq!(item.keys(), vec!["q", "c"]),
+                        ref v => panic!("{:?}", v),
+                    },
+                    v => panic!("{:?}", v),
+                }
+            },
+            v => panic!("{:?}", v),
+        }
+    }
+
+    #[test]
+    fn decimals_are_checked() {
+        assert_eq!(value(r#"{"$numberDecimal":"-1.5E+3"}"#), Ok(Value::Number(Number::Decimal128("-1.5E+3".to_string()))));
+        assert_eq!(value(r#"{"$numberDecimal":"nan"}"#), Ok(Value::Number(Number::Decimal128("NaN".to_string()))));
+        assert_eq!(value(r#"{"$numberDecimal":"-Inf"}"#), Ok(Value::Number(Number::Decimal128("-Infinity".to_string()))));
+        assert!(value(r#"{"$numberDecimal":"1); DROP TABLE x; --"}"#).is_err());
+        assert!(value(r#"{"$numberDecimal":"0x1F"}"#).is_err());
+        assert!(value(r#"{"$numberDecimal":""}"#).is_err());
+        assert!(value(r#"{"$numberDecimal":1.5}"#).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::mem;
+use value::{Record, Value};
+use extjson;
+
+/// How arrays are written when flattening a document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArrayMode {
+    /// One column per element, e.g. `tags.0`, `tags.1`.
+    Indexed,
+    /// The whole array as a JSON-encoded cell.
+    Json,
+}
+
+impl ArrayMode {
+    pub fn parse(mode: &str) -> Option<ArrayMode> {
+        match mode {
+            "indexed" => Some(ArrayMode::Indexed),
+            "json" => Some(ArrayMode::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Flattens embedded documents into dotted field names, as mongoexport does.
+#[derive(Clone, Debug)]
+pub struct Flatten {
+    /// Separator between the parts of a flattened name.
+    pub separator: String,
+    /// Levels of nesting to flatten; deeper values are JSON-encoded.
+    /// 1 keeps only top level fields, 0 flattens without limit.
+    pub max_depth: usize,
+    pub arrays: ArrayMode,
+}
+
+impl Default for Flatten {
+    fn default() -> Flatten {
+        Flatten {
+            separator: ".".to_string(),
+            max_depth: 0,
+            arrays: ArrayMode::Indexed,
+        }
+    }
+}
+
+impl Flatten {
+    pub fn record(&self, r: &Record, tz: bool) -> Record {
+        let mut flat = Record::new();
+        for (k, v) in r {
+            self.value(&mut flat, k, v, 1, tz);
+        }
+        flat
+    }
+
+    fn value(&self, flat: &mut Record, key: &str, v: &Value, depth: usize, tz: bool) {
+        let nested = self.max_depth == 0 || depth < self.max_depth;
+        match *v {
+            Value::Document(ref d) if nested && !d.is_empty() => {
+                for (k, v) in d {
+                    self.value(flat, &format!("{}{}{}", key, self.separator, k), v, depth + 1, tz);
+                }
+            },
+            Value::Array(ref a) if nested && !a.is_empty() && self.arrays == ArrayMode::Indexed => {
+                for (i, v) in a.iter().enumerate() {
+                    self.value(flat, &format!("{}{}{}", key, self.separator, i), v, depth + 1, tz);
+                }
+            },
+            Value::Document(_) | Value::Array(_) => {
+                flat.insert(key, Value::String(extjson::format(v, tz, true)));
+            },
+            _ => flat.insert(key, v.clone()),
+        }
+    }
+}
+
+/// Intermediate tree of dotted names used while unflattening. A branch may
+/// also have a value given for its own name, e.g. the `tags` cell beside
+/// `tags.0` of a document whose array was empty, which it becomes if it has
+/// nothing else.
+enum Node {
+    Leaf(Value),
+    Branch(Vec<(String, Node)>, Option<Value>),
+}
+
+impl Node {
+    fn insert(&mut self, parts: &[&str], v: Value) {
+        if let Node::Branch(ref mut children, _) = *self {
+            let pos = match children.iter().position(|c| c.0 == parts[0]) {
+                Some(pos) => pos,
+                None => {
+                    children.push((parts[0].to_string(), Node::Branch(Vec::new(), None)));
+                    children.len() - 1
+                },
+            };
+            let child = &mut children[pos].1;
+            if parts.len() == 1 {
+                match *child {
+                    Node::Branch(ref c, ref mut own) if !c.is_empty() => {
+                        if !v.is_null() {
+                            *own = Some(v);
+                        }
+                    },
+                    _ => *child = Node::Leaf(v),
+                }
+            }
+            else {
+                if let Node::Leaf(ref mut leaf) = *child {
+                    let own = Some(mem::replace(leaf, Value::Null)).filter(|v| !v.is_null());
+                    *child = Node::Branch(Vec::new(), own);
+                }
+                child.insert(&parts[1..], v);
+            }
+        }
+    }
+
+    /// A branch whose names are exactly 0..n becomes an array, any other a
+    /// document. A CSV row has empty cells for the fields other documents
+    /// have, so empty fields, trailing empty elements of arrays, and
+    /// sub-documents and arrays left empty, are left out rather than made
+    /// null. The root is at `depth` 0.
+    fn into_value(self, depth: usize) -> Option<Value> {
+        match self {
+            Node::Leaf(Value::Null) => None,
+            Node::Leaf(v) => Some(v),
+            Node::Branch(mut children, own) => {
+                let indexed = !children.is_empty() && {
+                    let mut indices: Vec<Option<usize>> = children.iter()
+                        .map(|c| c.0.parse::<usize>().ok().filter(|n| n.to_string() == c.0))
+                        .collect();
+                    indices.sort();
+                    indices.iter().enumerate().all(|(i, n)| *n == Some(i))
+                };
+                let value = if indexed {
+                    children.sort_by_key(|c| c.0.parse::<usize>().unwrap());
+                    let mut a: Vec<Value> = children.into_iter()
+                        .map(|c| c.1.into_value(depth + 1).unwrap_or(Value::Null))
+                        .collect();
+                    while a.last().is_some_and(Value::is_null) {
+                        a.pop();
+                    }
+                    Value::Array(a)
+                }
+                else {
+                    let mut r = Record::new();
+                    for (k, n) in children {
+                        if let Some(v) = n.into_value(depth + 1) {
+                            r.insert(&k, v);
+                        }
+                    }
+                    Value::Document(r)
+                };
+                match value {
+                    Value::Array(ref a) if a.is_empty() && depth > 0 => own,
+                    Value::Document(ref r) if r.is_empty() && depth > 0 => own,
+                    v => Some(v),
+                }
+            },
+        }
+    }
+}
+
+impl Flatten {
+    /// Rebuild embedded documents and arrays from dotted and indexed names.
+    pub fn unflatten(&self, r: &Record) -> Record {
+        let mut root = Node::Branch(Vec::new(), None);
+        for (k, v) in r {
+            let parts: Vec<&str> = if self.separator.is_empty() {
+                vec![k.as_ref()]
+            }
+            else {
+                k.split(self.separator.as_str()).collect()
+            };
+            root.insert(&parts, v.clone());
+        }
+        match root.into_value(0) {
+            Some(Value::Document(r)) => r,
+            // The top level is always a document, even with names 0..n.
+            Some(Value::Array(a)) => {
+                let mut r = Record::new();
+                for (i, v) in a.into_iter().enumerate() {
+                    r.insert(&i.to_string(), v);
+                }
+                r
+            },
+            _ => Record::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(fields: Vec<(&str, Value)>) -> Record {
+        let mut r = Record::new();
+        for (k, v) in fields {
+            r.insert(k, v);
+        }
+        r
+    }
+
+    fn strings(items: &[&str]) -> Value {
+        Value::Array(items.iter().map(|s| Value::String(s.to_string())).collect())
+    }
+
+    /// The rows of a CSV file of flattened records: every column of any
+    /// record, in the order first seen, empty where a record lacks it.
+    fn rows(records: &[Record]) -> Vec<Record> {
+        let flat: Vec<Record> = records.iter().map(|r| Flatten::default().record(r, false)).collect();
+        let mut columns: Vec<String> = Vec::new();
+        for r in &flat {
+            for k in r.keys() {
+                if !columns.contains(&k) {
+                    columns.push(k);
+                }
+            }
+        }
+        flat.iter()
+            .map(|r| {
+                let mut row = Record::new();
+                for c in &columns {
+                    row.insert(c, r.get(c).cloned().unwrap_or(Value::Null));
+                }
+                row
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flatten_documents_and_arrays() {
+        let r = doc(vec![("a", Value::Document(doc(vec![("b", Value::Bool(true))]))), ("tags", strings(&["x", "y"]))]);
+        let flat = Flatten::default().record(&r, false);
+        assert_eq!(flat.keys(), vec!["a.b", "tags.0", "tags.1"]);
+        let json = Flatten { arrays: ArrayMode::Json, ..Flatten::default() }.record(&r, false);
+        assert_eq!(json.get("tags"), Some(&Value::String(r#"["x","y"]"#.to_string())));
+    }
+
+    #[test]
+    fn arrays_round_trip() {
+        let records = vec![
+            doc(vec![("_id", Value::String("1".to_string())), ("tags", strings(&["a", "b"]))]),
+            doc(vec![("_id", Value::String("2".to_string())), ("tags", strings(&["c"])),
+                ("addr", Value::Document(doc(vec![("city", Value::String("Oslo".to_string()))])))]),
+            doc(vec![("_id", Value::String("3".to_string())), ("tags", Value::Array(Vec::new()))]),
+        ];
+        let mut rows = rows(&records);
+        // The CSV reader reads the JSON written for the empty array back as one.
+        assert_eq!(rows[2].get("tags"), Some(&Value::String("[]".to_string())));
+        rows[2].insert("tags", Value::Array(Vec::new()));
+        let unflattened: Vec<Record> = rows.iter().map(|r| Flatten::default().unflatten(r)).collect();
+        assert_eq!(unflattened, records);
+    }
+
+    #[test]
+    fn empty_cells_are_left_out() {
+        let row = doc(vec![("a", Value::Null), ("b.c", Value::Null), ("d.0", Value::Null), ("d.1", Value::Bool(true)),
+            ("e", Value::String(String::new()))]);
+        let expected = doc(vec![("d", Value::Array(vec![Value::Null, Value::Bool(true)])),
+            ("e", Value::String(String::new()))]);
+        assert_eq!(Flatten::default().unflatten(&row), expected);
+    }
+
+    #[test]
+    fn leaves_do_not_replace_branches() {
+        let row = doc(vec![("tags.0", Value::String("a".to_string())), ("tags", Value::Null)]);
+        assert_eq!(Flatten::default().unflatten(&row), doc(vec![("tags", strings(&["a"]))]));
+        let row = doc(vec![("tags", Value::Null), ("tags.0", Value::String("a".to_string()))]);
+        assert_eq!(Flatten::default().unflatten(&row), doc(vec![("tags", strings(&["a"]))]));
+    }
+}
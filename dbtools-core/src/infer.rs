@@ -0,0 +1,188 @@
+use rustc_serialize::json::Json;
+use std::fmt;
+use value::{Number, Value};
+use util::parse_date;
+use extjson;
+
+/// Type of a text column, from narrowest to widest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnType {
+    /// No non-empty value seen yet.
+    Null,
+    Integer,
+    Decimal,
+    Boolean,
+    Timestamp,
+    ObjectId,
+    /// Embedded document or array written as JSON.
+    Json,
+    Text,
+}
+
+fn is_integer(v: &str) -> bool {
+    let v = v.strip_prefix('-').unwrap_or(v);
+    // Leading zeros are kept as text, e.g. zip codes.
+    !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()) && (v == "0" || !v.starts_with('0'))
+}
+
+fn is_decimal(v: &str) -> bool {
+    let (mantissa, exponent) = match v.find(['e', 'E']) {
+        Some(i) => (&v[..i], Some(&v[i + 1..])),
+        None => (v, None),
+    };
+    if let Some(e) = exponent {
+        let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+        if e.is_empty() || !e.bytes().all(|b| b.is_ascii_digit()) {
+            return false;
+        }
+    }
+    match mantissa.find('.') {
+        Some(i) => {
+            let (int, frac) = (&mantissa[..i], &mantissa[i + 1..]);
+            let int_ok = int.is_empty() || int == "-" || is_integer(int) || int == "-0";
+            int_ok && !frac.is_empty() && frac.bytes().all(|b| b.is_ascii_digit())
+        },
+        None => exponent.is_some() && is_integer(mantissa),
+    }
+}
+
+fn boolean(v: &str) -> Option<bool> {
+    match v {
+        "true" | "TRUE" | "True" | "%!s(bool=true)" => Some(true),
+        "false" | "FALSE" | "False" | "%!s(bool=false)" => Some(false),
+        _ => None,
+    }
+}
+
+fn object_id(v: &str) -> Option<&str> {
+    v.strip_prefix("ObjectId(").and_then(|v| v.strip_suffix(')'))
+}
+
+impl ColumnType {
+    /// The narrowest type for a single value.
+    pub fn of(v: &str) -> ColumnType {
+        if v.is_empty() {
+            ColumnType::Null
+        }
+        else if is_integer(v) {
+            ColumnType::Integer
+        }
+        else if is_decimal(v) {
+            ColumnType::Decimal
+        }
+        else if boolean(v).is_some() {
+            ColumnType::Boolean
+        }
+        else if parse_date(v).is_some() {
+            ColumnType::Timestamp
+        }
+        else if object_id(v).is_some() {
+            ColumnType::ObjectId
+        }
+        else if (v.starts_with('{') || v.starts_with('[')) && Json::from_str(v).is_ok() {
+            ColumnType::Json
+        }
+        else {
+            ColumnType::Text
+        }
+    }
+
+    /// The narrowest type holding values of both types.
+    pub fn widen(self, other: ColumnType) -> ColumnType {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Null, b) => b,
+            (a, ColumnType::Null) => a,
+            (ColumnType::Integer, ColumnType::Decimal) | (ColumnType::Decimal, ColumnType::Integer) => ColumnType::Decimal,
+            _ => ColumnType::Text,
+        }
+    }
+
+    /// Convert a value of a column of this type. Empty values are NULL.
+    pub fn convert(self, v: &str) -> Value {
+        if v.is_empty() {
+            return Value::Null;
+        }
+        match self {
+            ColumnType::Integer | ColumnType::Decimal => {
+                Number::parse(v).map_or_else(|| Value::String(v.to_string()), Value::Number)
+            },
+            ColumnType::Boolean => Value::Bool(boolean(v) == Some(true)),
+            ColumnType::Timestamp => parse_date(v).map_or(Value::Null, Value::Date),
+            ColumnType::ObjectId => Value::ObjectId(object_id(v).unwrap_or(v).to_string()),
+            ColumnType::Json => Json::from_str(v).ok().and_then(|j| extjson::to_value_in_order(&j, v).ok()).unwrap_or(Value::Null),
+            ColumnType::Null | ColumnType::Text => Value::String(v.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ColumnType::Null => "null",
+            ColumnType::Integer => "integer",
+            ColumnType::Decimal => "decimal",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Timestamp => "timestamp",
+            ColumnType::ObjectId => "ObjectId",
+            ColumnType::Json => "JSON",
+            ColumnType::Text => "text",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A value which forced a column to a wider type.
+#[derive(Clone, Debug)]
+pub struct Widening {
+    pub column: String,
+    pub line: u64,
+    pub value: String,
+    pub from: ColumnType,
+    pub to: ColumnType,
+}
+
+impl fmt::Display for Widening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: column '{}' widened from {} to {} by '{}'",
+            self.line, self.column, self.from, self.to, self.value)
+    }
+}
+
+/// Infers the type of each column from every value in it.
+#[derive(Clone, Debug)]
+pub struct Inference {
+    pub columns: Vec<String>,
+    pub types: Vec<ColumnType>,
+    pub widenings: Vec<Widening>,
+}
+
+impl Inference {
+    pub fn new(columns: &[String]) -> Inference {
+        Inference {
+            columns: columns.to_vec(),
+            types: vec![ColumnType::Null; columns.len()],
+            widenings: Vec::new(),
+        }
+    }
+
+    /// Add a row, found on the given line of the input.
+    pub fn add<'a, I: IntoIterator<Item = &'a str>>(&mut self, line: u64, row: I) {
+        for (i, v) in row.into_iter().enumerate().take(self.types.len()) {
+            let from = self.types[i];
+            let to = from.widen(ColumnType::of(v));
+            if to != from {
+                if from != ColumnType::Null {
+                    self.widenings.push(Widening {
+                        column: self.columns[i].clone(),
+                        line,
+                        value: v.to_string(),
+                        from,
+                        to,
+                    });
+                }
+                self.types[i] = to;
+            }
+        }
+    }
+}
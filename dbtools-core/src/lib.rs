@@ -0,0 +1,27 @@
+/*
+dbtools-core
+Shared record model, readers and writers for the db-tools utilities.
+
+Copyright 2016 Sam Saint-Pettersen.
+Licensed under the MIT/X11 License.
+*/
+
+extern crate regex;
+extern crate rustc_serialize;
+extern crate chrono;
+extern crate csv as csvlib;
+
+pub mod error;
+pub mod value;
+pub mod util;
+pub mod extjson;
+pub mod flatten;
+pub mod mongo;
+pub mod bson;
+pub mod infer;
+pub mod reject;
+pub mod csv;
+pub mod sql;
+
+pub use error::Error;
+pub use value::{Number, Record, Value};
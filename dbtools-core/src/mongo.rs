@@ -0,0 +1,254 @@
+use rustc_serialize::json::{self, Json, ParserError};
+use std::cmp;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use error::Error;
+use util::{self, Input};
+use value::{Record, Value};
+use extjson;
+use bson::{self, Metadata};
+
+/// Reads a MongoDB JSON dump: a document per line, documents spread over
+/// lines, e.g. pretty-printed, or a JSON array of documents, as written with
+/// `--array`. The input is read a document at a time, so only the document
+/// being read is held in memory.
+pub struct MongoReader {
+    input: String,
+    reader: BufReader<Box<dyn Read>>,
+    /// Line and column, from 0, of the next byte.
+    line: u64,
+    column: u64,
+    /// Inside a top-level array.
+    array: bool,
+    done: bool,
+}
+
+impl MongoReader {
+    pub fn open(input: &Input) -> Result<MongoReader, Error> {
+        let mut reader = MongoReader {
+            input: input.name().to_string(),
+            reader: BufReader::new(input.reader()?),
+            line: 0,
+            column: 0,
+            array: false,
+            done: false,
+        };
+        let bom = reader.reader.fill_buf().map_err(|e| Error::io(input.name(), e))?.starts_with(b"\xEF\xBB\xBF");
+        if bom {
+            reader.reader.consume(3);
+        }
+        Ok(reader)
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().cloned())
+    }
+
+    fn bump(&mut self, b: u8) {
+        self.reader.consume(1);
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 0;
+        }
+        else {
+            self.column += 1;
+        }
+    }
+
+    /// The text of the next value. It ends where its brackets balance, or,
+    /// for a malformed value, at a line break inside a string or, outside an
+    /// array, at a `{` starting a line, so the documents after it can still
+    /// be read.
+    fn value(&mut self) -> io::Result<Vec<u8>> {
+        let mut text = Vec::new();
+        let (mut depth, mut string, mut escaped) = (0, false, false);
+        while let Some(b) = self.peek()? {
+            if string {
+                if b == b'\n' {
+                    break;
+                }
+                if escaped {
+                    escaped = false;
+                }
+                else if b == b'\\' {
+                    escaped = true;
+                }
+                else if b == b'"' {
+                    string = false;
+                }
+            }
+            else {
+                let start = depth == 0 && !text.is_empty();
+                match b {
+                    b'{' | b'[' if start => break,
+                    b'{' if depth > 0 && self.column == 0 && !self.array => break,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' if depth > 0 => depth -= 1,
+                    b'"' if start => break,
+                    b'"' => string = true,
+                    b',' | b']' | b'}' if depth == 0 && !text.is_empty() => break,
+                    b if depth == 0 && b.is_ascii_whitespace() => break,
+                    _ => (),
+                }
+            }
+            self.bump(b);
+            text.push(b);
+            if depth == 0 && !string && (b == b'}' || b == b']') {
+                break;
+            }
+        }
+        // An unterminated value is reported where its last line ends.
+        while text.last().is_some_and(|b| b.is_ascii_whitespace()) {
+            text.pop();
+        }
+        Ok(text)
+    }
+
+    /// Parse a value starting at `line` and `column` as a document.
+    fn parse(&self, text: &[u8], line: u64, column: u64) -> Result<Record, Error> {
+        let error = |l: u64, c: u64, message: &str| {
+            let column = if l <= 1 { column + c } else { c };
+            Error::parse(line + cmp::max(l, 1), column, message).in_file(&self.input).in_record(text)
+        };
+        let text = String::from_utf8(text.to_vec()).map_err(|e| error(0, 0, &format!("Invalid UTF-8: {}", e)))?;
+        match Json::from_str(&text) {
+            Ok(json) => {
+                match extjson::to_value_in_order(&json, &text) {
+                    Ok(Value::Document(r)) => Ok(r),
+                    Ok(_) => Err(error(0, 1, "Expected a document")),
+                    Err(message) => Err(error(0, 1, &message)),
+                }
+            },
+            Err(ParserError::SyntaxError(code, l, c)) => Err(error(l as u64, c as u64, json::error_str(code))),
+            Err(ParserError::IoError(e)) => Err(Error::io(&self.input, e)),
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Option<(Vec<u8>, u64, u64)>> {
+        loop {
+            let b = match self.peek()? {
+                Some(b) => b,
+                None => return Ok(None),
+            };
+            match b {
+                b if b.is_ascii_whitespace() => (),
+                b'[' if !self.array => self.array = true,
+                b']' if self.array => self.array = false,
+                // Commas between the documents of an array.
+                b',' if self.array => (),
+                _ => {
+                    let (line, column) = (self.line, self.column);
+                    return self.value().map(|text| Some((text, line, column)));
+                },
+            }
+            self.bump(b);
+        }
+    }
+}
+
+impl Iterator for MongoReader {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Result<Record, Error>> {
+        if self.done {
+            return None;
+        }
+        match self.read() {
+            Ok(Some((text, line, column))) => Some(self.parse(&text, line, column)),
+            Ok(None) => None,
+            Err(e) => {
+                self.done = true;
+                Some(Err(Error::io(&self.input, e)))
+            },
+        }
+    }
+}
+
+/// Writes records as a MongoDB JSON dump.
+#[derive(Default)]
+pub struct MongoWriter {
+    /// Use "Z" as timezone for timestamps rather than +0000.
+    pub tz: bool,
+    /// Write ObjectIds, dates and binary data as Extended JSON wrappers.
+    pub mongo_types: bool,
+    /// Output the documents as a JSON array.
+    pub array: bool,
+    /// Output BSON, as `mongodump` does, with a metadata file beside it so
+    /// that `mongorestore` can restore it.
+    pub bson: bool,
+}
+
+impl MongoWriter {
+    pub fn format_record(&self, r: &Record) -> String {
+        extjson::format_document(r, self.tz, self.mongo_types)
+    }
+
+    /// Start a dump file, opening the array if there is one.
+    pub fn create(&self, output: &str) -> Result<MongoOutput<'_>, Error> {
+        let mut out = BufWriter::new(util::create(output)?);
+        if self.array && !self.bson {
+            writeln!(out, "[").map_err(|e| Error::io(output, e))?;
+        }
+        Ok(MongoOutput { writer: self, path: output.to_string(), out, count: 0 })
+    }
+
+    pub fn write<I: IntoIterator<Item = Result<Record, Error>>>(&self, records: I, output: &str)
+    -> Result<(), Error> {
+        let mut out = self.create(output)?;
+        for r in records {
+            out.write(&r?)?;
+        }
+        out.finish()
+    }
+}
+
+/// A dump file being written, one document per record.
+pub struct MongoOutput<'a> {
+    writer: &'a MongoWriter,
+    path: String,
+    out: BufWriter<Box<dyn Write>>,
+    count: usize,
+}
+
+impl<'a> MongoOutput<'a> {
+    pub fn write(&mut self, r: &Record) -> Result<(), Error> {
+        self.write_document(r).map_err(|e| Error::io(&self.path, e))
+    }
+
+    fn write_document(&mut self, r: &Record) -> io::Result<()> {
+        if self.writer.bson {
+            self.count += 1;
+            return self.out.write_all(&bson::encode_document(r));
+        }
+        // The line of the last document is ended here, as only now is it
+        // known whether a comma is needed.
+        if self.count > 0 {
+            writeln!(self.out, "{}", if self.writer.array { "," } else { "" })?;
+        }
+        write!(self.out, "{}", self.writer.format_record(r))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// End the dump, and write the metadata file of a BSON dump, named after
+    /// it, e.g. `people.metadata.json` for `people.bson`.
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.end().map_err(|e| Error::io(&self.path, e))?;
+        if self.writer.bson && !util::is_stdio(&self.path) {
+            Metadata::new(&util::file_stem(&self.path)).write(&Metadata::file(&self.path))?;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        if self.writer.bson {
+            return self.out.flush();
+        }
+        if self.count > 0 {
+            writeln!(self.out)?;
+        }
+        if self.writer.array {
+            writeln!(self.out, "]")?;
+        }
+        self.out.flush()
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use error::Error;
+
+/// What to do with a record which cannot be read.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnError {
+    /// Stop the conversion.
+    #[default]
+    Fail,
+    /// Leave the record out.
+    Skip,
+    /// Leave the record out and write it to a reject file.
+    Reject,
+}
+
+impl OnError {
+    pub fn parse(mode: &str) -> Option<OnError> {
+        match mode.to_lowercase().as_ref() {
+            "fail" => Some(OnError::Fail),
+            "skip" => Some(OnError::Skip),
+            "reject" => Some(OnError::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// Applies an `OnError` policy to the records of a reader. Rejected records
+/// are written as they were read to the reject file, if there is one, each
+/// after a line giving its position and the reason, e.g.
+///
+/// ```text
+/// # people.csv:12: Row has 3 fields but the header has 2
+/// 7,Ann,extra
+/// ```
+pub struct Recover<I> {
+    records: I,
+    on_error: OnError,
+    reject_file: String,
+    rejects: Option<BufWriter<File>>,
+    /// Why records were left out, each with how many, in the order first seen.
+    reasons: Vec<(String, u64)>,
+}
+
+impl<I> Recover<I> {
+    /// Without a reject file, rejected records are only counted.
+    pub fn new(records: I, on_error: OnError) -> Recover<I> {
+        Recover {
+            records,
+            on_error,
+            reject_file: String::new(),
+            rejects: None,
+            reasons: Vec::new(),
+        }
+    }
+
+    /// Write rejected records to a file, created with the first of them.
+    pub fn reject_to(mut self, file: &str) -> Recover<I> {
+        self.reject_file = file.to_string();
+        self
+    }
+
+    /// Number of records left out.
+    pub fn count(&self) -> u64 {
+        self.reasons.iter().map(|r| r.1).sum()
+    }
+
+    /// A line on the records left out and why, if any, e.g. "Skipped 3
+    /// records (2: Row has 1 fields but the header has 2; 1: ...)." Past
+    /// three reasons, only how many more there are is given.
+    pub fn summary(&self) -> Option<String> {
+        if self.count() == 0 {
+            return None;
+        }
+        let mut reasons: Vec<String> = if self.reasons.len() == 1 {
+            vec![self.reasons[0].0.clone()]
+        }
+        else {
+            self.reasons.iter().take(3).map(|r| format!("{}: {}", r.1, r.0)).collect()
+        };
+        if self.reasons.len() > 3 {
+            reasons.push(format!("{} other reasons", self.reasons.len() - 3));
+        }
+        let plural = if self.count() == 1 { "" } else { "s" };
+        let records = format!("{} record{} ({})", self.count(), plural, reasons.join("; "));
+        match self.on_error {
+            OnError::Reject if !self.reject_file.is_empty() => {
+                Some(format!("Rejected {} to '{}'.", records, self.reject_file))
+            },
+            _ => Some(format!("Skipped {}.", records)),
+        }
+    }
+
+    fn reject(&mut self, error: &Error, raw: &[u8]) -> Result<(), Error> {
+        if self.reject_file.is_empty() {
+            return Ok(());
+        }
+        if self.rejects.is_none() {
+            let f = File::create(&self.reject_file).map_err(|e| Error::io(&self.reject_file, e))?;
+            self.rejects = Some(BufWriter::new(f));
+        }
+        let path = &self.reject_file;
+        if let Some(ref mut w) = self.rejects {
+            let written = writeln!(w, "# {}", error)
+                .and_then(|_| w.write_all(raw))
+                .and_then(|_| writeln!(w))
+                .and_then(|_| w.flush());
+            written.map_err(|e| Error::io(path, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T, I: Iterator<Item = Result<T, Error>>> Iterator for Recover<I> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Result<T, Error>> {
+        loop {
+            match self.records.next()? {
+                Err(Error::BadRecord { error, raw }) if self.on_error != OnError::Fail => {
+                    let reason = error.message();
+                    match self.reasons.iter_mut().find(|r| r.0 == reason) {
+                        Some(r) => r.1 += 1,
+                        None => self.reasons.push((reason, 1)),
+                    }
+                    if self.on_error == OnError::Reject {
+                        if let Err(e) = self.reject(&error, &raw) {
+                            return Some(Err(e));
+                        }
+                    }
+                },
+                r => return Some(r),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bad(line: u64, message: &str) -> Result<u32, Error> {
+        Err(Error::schema("people.csv", line, message).in_record(b"raw"))
+    }
+
+    #[test]
+    fn summary_gives_the_reasons() {
+        let short = "Row has 1 fields but the header has 2";
+        let mut rows = Recover::new(vec![Ok(1), bad(3, short)].into_iter(), OnError::Skip);
+        assert_eq!(rows.by_ref().map(|r| r.unwrap()).collect::<Vec<u32>>(), vec![1]);
+        assert_eq!(rows.summary(), Some(format!("Skipped 1 record ({}).", short)));
+
+        let records = vec![bad(2, short), bad(3, "a"), bad(4, short), bad(5, "b"), bad(6, "c"), bad(7, "d")];
+        let mut rows = Recover::new(records.into_iter(), OnError::Skip);
+        assert_eq!(rows.by_ref().count(), 0);
+        assert_eq!(rows.summary(), Some(format!("Skipped 6 records (2: {}; 1: a; 1: b; 2 other reasons).", short)));
+    }
+
+    #[test]
+    fn failing_keeps_the_error() {
+        let mut rows = Recover::new(vec![bad(2, "a")].into_iter(), OnError::Fail);
+        assert!(rows.next().unwrap().is_err());
+        assert_eq!(rows.summary(), None);
+    }
+}
@@ -0,0 +1,298 @@
+use chrono::NaiveDateTime;
+use rustc_serialize::hex::ToHex;
+use util::format_sql_timestamp;
+use super::{SqlIndex, SqlTable};
+
+/// Portable column type, named by each dialect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SqlType {
+    Char(usize),
+    Varchar(usize),
+    /// Text of any length.
+    Text,
+    Date,
+    Timestamp,
+    Integer,
+    BigInt,
+    Decimal(usize, usize),
+    Double,
+    Boolean,
+    Blob,
+}
+
+/// SQL flavour of a generated dump.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Dialect {
+    #[default]
+    MySql,
+    PostgreSql,
+    Sqlite,
+    SqlServer,
+}
+
+impl SqlType {
+    /// The portable type of a declared column type, e.g. `DECIMAL(10,2)` or
+    /// `CHARACTER VARYING(30)`, if it has one.
+    pub fn parse(declared: &str) -> Option<SqlType> {
+        let declared = declared.to_uppercase();
+        let base: Vec<&str> = declared.split('(').next().unwrap_or("").split_whitespace()
+            .filter(|w| !["UNSIGNED", "SIGNED", "ZEROFILL"].contains(w))
+            .collect();
+        let base = base.join(" ");
+        let args: Vec<&str> = declared.find('(')
+            .map_or("", |i| declared[i + 1..].split(')').next().unwrap_or(""))
+            .split(',').map(|a| a.trim()).filter(|a| !a.is_empty()).collect();
+        let arg = |i: usize| args.get(i).and_then(|a| a.parse::<usize>().ok());
+        let unsigned = declared.contains("UNSIGNED");
+        Some(match base.as_str() {
+            "TINYINT" if arg(0) == Some(1) => SqlType::Boolean,
+            "BOOLEAN" | "BOOL" => SqlType::Boolean,
+            "BIT" if arg(0).unwrap_or(1) == 1 => SqlType::Boolean,
+            "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT2" | "SMALLSERIAL" => SqlType::Integer,
+            "INT" | "INTEGER" | "INT4" | "SERIAL" if unsigned => SqlType::BigInt,
+            "INT" | "INTEGER" | "INT4" | "SERIAL" => SqlType::Integer,
+            "BIGINT" | "INT8" | "BIGSERIAL" if unsigned => SqlType::Decimal(20, 0),
+            "BIGINT" | "INT8" | "BIGSERIAL" => SqlType::BigInt,
+            "DECIMAL" | "NUMERIC" | "DEC" | "FIXED" => SqlType::Decimal(arg(0)?, arg(1).unwrap_or(0)),
+            "FLOAT" | "DOUBLE" | "DOUBLE PRECISION" | "REAL" | "FLOAT4" | "FLOAT8" => SqlType::Double,
+            "CHAR" | "CHARACTER" | "NCHAR" => SqlType::Char(arg(0).unwrap_or(1)),
+            "VARCHAR" | "NVARCHAR" | "CHARACTER VARYING" | "VARCHAR2" => {
+                match arg(0) {
+                    Some(n) => SqlType::Varchar(n),
+                    None => SqlType::Text,
+                }
+            },
+            "TEXT" | "TINYTEXT" | "MEDIUMTEXT" | "LONGTEXT" | "NTEXT" | "CLOB" | "JSON" | "JSONB" | "UUID" | "ENUM"
+            | "SET" => SqlType::Text,
+            "DATE" => SqlType::Date,
+            "DATETIME" | "DATETIME2" | "SMALLDATETIME" | "TIMESTAMP" | "TIMESTAMPTZ" => SqlType::Timestamp,
+            _ if base.starts_with("TIMESTAMP") => SqlType::Timestamp,
+            "BLOB" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BINARY" | "VARBINARY" | "BYTEA" | "IMAGE" => SqlType::Blob,
+            _ => return None,
+        })
+    }
+}
+
+impl Dialect {
+    pub fn parse(name: &str) -> Option<Dialect> {
+        match name.to_lowercase().as_ref() {
+            "mysql" | "mariadb" => Some(Dialect::MySql),
+            "postgresql" | "postgres" | "pg" => Some(Dialect::PostgreSql),
+            "sqlite" | "sqlite3" => Some(Dialect::Sqlite),
+            "sqlserver" | "mssql" => Some(Dialect::SqlServer),
+            _ => None,
+        }
+    }
+
+    /// Quote a table, column or database name.
+    pub fn quote(self, name: &str) -> String {
+        match self {
+            Dialect::MySql => format!("`{}`", name.replace("`", "``")),
+            Dialect::PostgreSql | Dialect::Sqlite => format!("\"{}\"", name.replace("\"", "\"\"")),
+            Dialect::SqlServer => format!("[{}]", name.replace("]", "]]")),
+        }
+    }
+
+    pub fn type_name(self, t: SqlType) -> String {
+        let name = match (self, t) {
+            (Dialect::SqlServer, SqlType::Char(n)) => return format!("NCHAR({})", n),
+            (_, SqlType::Char(n)) => return format!("CHAR({})", n),
+            (Dialect::SqlServer, SqlType::Varchar(n)) => return format!("NVARCHAR({})", n),
+            (_, SqlType::Varchar(n)) => return format!("VARCHAR({})", n),
+            (Dialect::MySql, SqlType::Text) => "LONGTEXT",
+            (Dialect::SqlServer, SqlType::Text) => "NVARCHAR(MAX)",
+            (_, SqlType::Text) => "TEXT",
+            (_, SqlType::Decimal(p, s)) => return format!("DECIMAL({}, {})", p, s),
+            (_, SqlType::Date) => "DATE",
+            (Dialect::PostgreSql, SqlType::Timestamp) => "TIMESTAMPTZ",
+            (Dialect::Sqlite, SqlType::Timestamp) => "DATETIME",
+            (Dialect::SqlServer, SqlType::Timestamp) => "DATETIME2",
+            (_, SqlType::Timestamp) => "TIMESTAMP",
+            (Dialect::Sqlite, SqlType::Integer) | (Dialect::Sqlite, SqlType::BigInt) => "INTEGER",
+            (_, SqlType::Integer) => "INTEGER",
+            (_, SqlType::BigInt) => "BIGINT",
+            (Dialect::PostgreSql, SqlType::Double) => "DOUBLE PRECISION",
+            (Dialect::Sqlite, SqlType::Double) => "REAL",
+            (Dialect::SqlServer, SqlType::Double) => "FLOAT",
+            (_, SqlType::Double) => "DOUBLE",
+            (Dialect::Sqlite, SqlType::Boolean) => "INTEGER",
+            (Dialect::SqlServer, SqlType::Boolean) => "BIT",
+            (_, SqlType::Boolean) => "BOOLEAN",
+            (Dialect::PostgreSql, SqlType::Blob) => "BYTEA",
+            (Dialect::SqlServer, SqlType::Blob) => "VARBINARY(MAX)",
+            (_, SqlType::Blob) => "BLOB",
+        };
+        name.to_string()
+    }
+
+    /// Backslashes in string literals are escapes, rather than literal
+    /// backslashes as in standard SQL.
+    pub fn backslash_escapes(self) -> bool {
+        self == Dialect::MySql
+    }
+
+    /// Quote a string literal. MySQL also treats backslashes as escapes.
+    pub fn string(self, s: &str) -> String {
+        match self {
+            Dialect::MySql => format!("'{}'", s.replace("\\", "\\\\").replace("'", "''")),
+            Dialect::SqlServer => format!("N'{}'", s.replace("'", "''")),
+            Dialect::PostgreSql | Dialect::Sqlite => format!("'{}'", s.replace("'", "''")),
+        }
+    }
+
+    pub fn boolean(self, b: bool) -> String {
+        match self {
+            Dialect::MySql | Dialect::PostgreSql => b.to_string().to_uppercase(),
+            Dialect::Sqlite | Dialect::SqlServer => (b as u8).to_string(),
+        }
+    }
+
+    /// Timestamps are UTC; PostgreSQL is told so, as the column has a time zone.
+    pub fn timestamp(self, d: &NaiveDateTime) -> String {
+        match self {
+            Dialect::PostgreSql => format!("'{}+00'", format_sql_timestamp(d)),
+            _ => format!("'{}'", format_sql_timestamp(d)),
+        }
+    }
+
+    pub fn binary(self, bytes: &[u8]) -> String {
+        match self {
+            Dialect::MySql | Dialect::Sqlite => format!("X'{}'", bytes.to_hex()),
+            Dialect::PostgreSql => format!("'\\x{}'", bytes.to_hex()),
+            Dialect::SqlServer => format!("0x{}", bytes.to_hex()),
+        }
+    }
+
+    /// Statement starting a dump. PostgreSQL is told its strings have literal
+    /// backslashes, and SQLite loads the dump in a transaction, as sqlite3
+    /// writes it; either tells a reader the strings are not MySQL's.
+    pub fn begin(self) -> Option<&'static str> {
+        match self {
+            Dialect::PostgreSql => Some("SET standard_conforming_strings = on;"),
+            Dialect::Sqlite => Some("BEGIN TRANSACTION;"),
+            Dialect::MySql | Dialect::SqlServer => None,
+        }
+    }
+
+    /// Statement ending a dump.
+    pub fn end(self) -> Option<&'static str> {
+        match self {
+            Dialect::Sqlite => Some("COMMIT;"),
+            _ => None,
+        }
+    }
+
+    /// Statement selecting the database, or schema in PostgreSQL. SQLite
+    /// has one database per file, so there is none.
+    pub fn use_database(self, db: &str) -> Option<String> {
+        match self {
+            Dialect::MySql | Dialect::SqlServer => Some(format!("USE {};", self.quote(db))),
+            Dialect::PostgreSql => Some(format!("SET search_path TO {};", self.quote(db))),
+            Dialect::Sqlite => None,
+        }
+    }
+
+    pub fn create_table(self, table: &str) -> String {
+        match self {
+            // SQL Server has no IF NOT EXISTS; the table was just dropped anyway.
+            Dialect::SqlServer => format!("CREATE TABLE {} (", self.quote(table)),
+            _ => format!("CREATE TABLE IF NOT EXISTS {} (", self.quote(table)),
+        }
+    }
+
+    /// A column of a key or index. MySQL only indexes a prefix of TEXT and
+    /// BLOB columns, so it is given the first 255 characters or bytes.
+    pub fn key_column(self, table: &SqlTable, column: &str) -> String {
+        let ty = table.columns.iter().find(|c| c.0 == column).map(|c| c.1);
+        match (self, ty) {
+            (Dialect::MySql, Some(SqlType::Text)) | (Dialect::MySql, Some(SqlType::Blob)) => {
+                format!("{}(255)", self.quote(column))
+            },
+            _ => self.quote(column),
+        }
+    }
+
+    pub fn create_index(self, table: &SqlTable, index: &SqlIndex) -> String {
+        let columns: Vec<String> = index.columns.iter()
+            .map(|&(ref c, descending)| format!("{}{}", self.key_column(table, c), if descending { " DESC" } else { "" }))
+            .collect();
+        let unique = if index.unique { "UNIQUE " } else { "" };
+        format!("CREATE {}INDEX {} ON {} ({});", unique, self.quote(&index.name), self.quote(&table.name),
+                columns.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sql::parser::{Literal, Parser, Statement};
+
+    const DIALECTS: [Dialect; 4] = [Dialect::MySql, Dialect::PostgreSql, Dialect::Sqlite, Dialect::SqlServer];
+
+    #[test]
+    fn quoted_names() {
+        assert_eq!(Dialect::MySql.quote("a`b"), "`a``b`");
+        assert_eq!(Dialect::PostgreSql.quote("a\"b"), "\"a\"\"b\"");
+        assert_eq!(Dialect::Sqlite.quote("a b"), "\"a b\"");
+        assert_eq!(Dialect::SqlServer.quote("a]b"), "[a]]b]");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(Dialect::MySql.string(r"it's C:\dir\"), r"'it''s C:\\dir\\'");
+        assert_eq!(Dialect::PostgreSql.string(r"it's C:\dir\"), r"'it''s C:\dir\'");
+        assert_eq!(Dialect::Sqlite.string(r"it's C:\dir\"), r"'it''s C:\dir\'");
+        assert_eq!(Dialect::SqlServer.string(r"it's C:\dir\"), r"N'it''s C:\dir\'");
+    }
+
+    #[test]
+    fn binary() {
+        assert_eq!(Dialect::MySql.binary(&[0, 255]), "X'00ff'");
+        assert_eq!(Dialect::PostgreSql.binary(&[0, 255]), r"'\x00ff'");
+        assert_eq!(Dialect::SqlServer.binary(&[0, 255]), "0x00ff");
+    }
+
+    /// A dump of each dialect is read back as written, whether the reader
+    /// is told its dialect or finds it from the dump.
+    #[test]
+    fn strings_read_back() {
+        let text = "C:\\new\\dir\\ it's\na\\nb";
+        for &d in &DIALECTS {
+            let sql = format!("{}\nINSERT INTO t VALUES ({});\n{}", d.begin().unwrap_or(""), d.string(text), d.end().unwrap_or(""));
+            for &dialect in &[None, Some(d)] {
+                let rows: Vec<Vec<Literal>> = Parser::new(sql.chars(), dialect)
+                    .filter_map(|s| match s.unwrap() {
+                        Statement::Insert(i) => Some(i.rows[0].clone()),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(rows, vec![vec![Literal::String(text.to_string())]], "{:?} read as {:?}", d, dialect);
+            }
+        }
+    }
+
+    #[test]
+    fn declared_types() {
+        assert_eq!(SqlType::parse("DECIMAL(10,2) UNSIGNED"), Some(SqlType::Decimal(10, 2)));
+        assert_eq!(SqlType::parse("int(11) unsigned"), Some(SqlType::BigInt));
+        assert_eq!(SqlType::parse("TINYINT(1)"), Some(SqlType::Boolean));
+        assert_eq!(SqlType::parse("CHARACTER VARYING(30)"), Some(SqlType::Varchar(30)));
+        assert_eq!(SqlType::parse("VARCHAR"), Some(SqlType::Text));
+        assert_eq!(SqlType::parse("TIMESTAMP WITHOUT TIME ZONE"), Some(SqlType::Timestamp));
+        assert_eq!(SqlType::parse("DECIMAL"), None);
+        assert_eq!(SqlType::parse("GEOMETRY"), None);
+    }
+
+    #[test]
+    fn indexed_text_has_a_prefix() {
+        let mut table = SqlTable::new("t", vec![("name".to_string(), SqlType::Text),
+                                                ("code".to_string(), SqlType::Varchar(10))]);
+        let index = SqlIndex { name: "t_name".to_string(), columns: vec![("name".to_string(), false),
+                                                                         ("code".to_string(), true)], unique: true };
+        table.indexes.push(index.clone());
+        assert_eq!(Dialect::MySql.create_index(&table, &index),
+                   "CREATE UNIQUE INDEX `t_name` ON `t` (`name`(255), `code` DESC);");
+        assert_eq!(Dialect::PostgreSql.create_index(&table, &index),
+                   "CREATE UNIQUE INDEX \"t_name\" ON \"t\" (\"name\", \"code\" DESC);");
+    }
+}
@@ -0,0 +1,374 @@
+use std::iter::Peekable;
+use error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    /// Keyword or bare identifier.
+    Word(String),
+    /// Backquoted, double-quoted or bracketed identifier.
+    Ident(String),
+    /// Single-quoted string literal with escapes resolved.
+    Str(String),
+    Number(String),
+    /// Hex literal (`0xCAFE` or `X'CAFE'`), digits only.
+    Hex(String),
+    /// Bit literal (`0b101` or `b'101'`), digits only.
+    Bits(String),
+    Symbol(char),
+}
+
+/// Splits SQL text into tokens, skipping whitespace and comments.
+pub struct Lexer<I: Iterator<Item = char>> {
+    chars: Peekable<I>,
+    line: u64,
+    column: u64,
+    /// Where the last token started.
+    start: (u64, u64),
+    /// Text read since the token marked by `mark()`.
+    raw: String,
+    mark: bool,
+    /// Backslashes in single-quoted strings are escapes, as in MySQL, rather
+    /// than standard SQL's literal backslashes.
+    pub backslash_escapes: bool,
+}
+
+impl<I: Iterator<Item = char>> Lexer<I> {
+    pub fn new(chars: I) -> Lexer<I> {
+        Lexer {
+            chars: chars.peekable(),
+            line: 1,
+            column: 0,
+            start: (1, 0),
+            raw: String::new(),
+            mark: false,
+            backslash_escapes: true,
+        }
+    }
+
+    /// Keep the text read from the start of the next token on, e.g. for the
+    /// text of a statement.
+    pub fn mark(&mut self) {
+        self.mark = true;
+    }
+
+    /// The text read since the marked token, or since the start of the
+    /// last `read_line()`.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Current line in the input.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Line and column where the last token started, for error messages.
+    pub fn position(&self) -> (u64, u64) {
+        self.start
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+            self.column = 0;
+        }
+        else if c.is_some() {
+            self.column += 1;
+        }
+        if let Some(c) = c {
+            self.raw.push(c);
+        }
+        c
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn skip_block_comment(&mut self) -> Result<(), Error> {
+        let mut last = '\0';
+        while let Some(c) = self.bump() {
+            if last == '*' && c == '/' {
+                return Ok(());
+            }
+            last = c;
+        }
+        Err(Error::parse(self.start.0, self.start.1, "Unterminated comment"))
+    }
+
+    fn quoted(&mut self, quote: char, escapes: bool) -> Result<String, Error> {
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => {
+                    // A doubled quote stands for the quote itself.
+                    if self.chars.peek() == Some(&quote) {
+                        self.bump();
+                        s.push(quote);
+                    }
+                    else {
+                        return Ok(s);
+                    }
+                },
+                Some('\\') if escapes => {
+                    match self.bump() {
+                        Some('n') => s.push('\n'),
+                        Some('t') => s.push('\t'),
+                        Some('r') => s.push('\r'),
+                        Some('0') => s.push('\0'),
+                        Some('Z') => s.push('\x1a'),
+                        Some(c) => s.push(c),
+                        None => break,
+                    }
+                },
+                Some(c) => s.push(c),
+                None => break,
+            }
+        }
+        Err(Error::parse(self.start.0, self.start.1, "Unterminated quoted text"))
+    }
+
+    /// PostgreSQL dollar-quoted text, e.g. `$$...$$` or `$body$...$body$`.
+    fn dollar_quoted(&mut self, tag: &str) -> Result<String, Error> {
+        let mut s = String::new();
+        while let Some(c) = self.bump() {
+            s.push(c);
+            if s.ends_with(tag) {
+                s.truncate(s.len() - tag.len());
+                return Ok(s);
+            }
+        }
+        Err(Error::parse(self.start.0, self.start.1, "Unterminated quoted text"))
+    }
+
+    /// The raw text of the rest of the current line, for the data of a
+    /// COPY statement, or None at the end of the input.
+    pub fn read_line(&mut self) -> Option<String> {
+        self.raw.clear();
+        let mut line = String::new();
+        loop {
+            match self.bump() {
+                Some('\n') => return Some(line),
+                Some(c) => line.push(c),
+                None if line.is_empty() => return None,
+                None => return Some(line),
+            }
+        }
+    }
+
+    fn number(&mut self, first: char) -> String {
+        let mut n = first.to_string();
+        let mut last = first;
+        while let Some(&c) = self.chars.peek() {
+            let exponent = (c == '+' || c == '-') && (last == 'e' || last == 'E');
+            if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent {
+                n.push(c);
+                last = c;
+                self.bump();
+            }
+            else {
+                break;
+            }
+        }
+        n
+    }
+
+    fn word(&mut self, first: char) -> String {
+        let mut w = first.to_string();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '$' {
+                w.push(c);
+                self.bump();
+            }
+            else {
+                break;
+            }
+        }
+        w
+    }
+
+    pub fn next_token(&mut self) -> Option<Result<Token, Error>> {
+        loop {
+            let c = self.bump()?;
+            self.start = (self.line, self.column);
+            let offset = self.raw.len() - c.len_utf8();
+            let next = self.chars.peek().cloned();
+            let token = match c {
+                c if c.is_whitespace() => continue,
+                '-' if next == Some('-') => {
+                    self.skip_line();
+                    continue;
+                },
+                '#' => {
+                    self.skip_line();
+                    continue;
+                },
+                '/' if next == Some('*') => {
+                    self.bump();
+                    match self.skip_block_comment() {
+                        Ok(()) => continue,
+                        Err(e) => Err(e),
+                    }
+                },
+                '\'' => {
+                    let escapes = self.backslash_escapes;
+                    self.quoted('\'', escapes).map(Token::Str)
+                },
+                '`' => self.quoted('`', false).map(Token::Ident),
+                '"' => {
+                    let escapes = self.backslash_escapes;
+                    self.quoted('"', escapes).map(Token::Ident)
+                },
+                '[' => self.quoted(']', false).map(Token::Ident),
+                '0' if next == Some('x') || next == Some('X') => {
+                    self.bump();
+                    Ok(Token::Hex(self.word('0')[1..].to_string()))
+                },
+                'x' | 'X' if next == Some('\'') => {
+                    self.bump();
+                    self.quoted('\'', false).map(Token::Hex)
+                },
+                '0' if next == Some('b') || next == Some('B') => {
+                    self.bump();
+                    Ok(Token::Bits(self.word('0')[1..].to_string()))
+                },
+                'b' | 'B' if next == Some('\'') => {
+                    self.bump();
+                    self.quoted('\'', false).map(Token::Bits)
+                },
+                // National character string, as written for SQL Server,
+                // where backslashes are not escapes.
+                'n' | 'N' if next == Some('\'') => {
+                    self.bump();
+                    self.quoted('\'', false).map(Token::Str)
+                },
+                // PostgreSQL string with backslash escapes.
+                'e' | 'E' if next == Some('\'') => {
+                    self.bump();
+                    self.quoted('\'', true).map(Token::Str)
+                },
+                '$' if next == Some('$') || next.is_some_and(|n| n.is_alphabetic() || n == '_') => {
+                    let mut tag = "$".to_string();
+                    while let Some(&c) = self.chars.peek() {
+                        if c.is_alphanumeric() || c == '_' {
+                            tag.push(c);
+                            self.bump();
+                        }
+                        else {
+                            if c == '$' {
+                                tag.push(c);
+                                self.bump();
+                            }
+                            break;
+                        }
+                    }
+                    if tag.len() > 1 && tag.ends_with('$') {
+                        self.dollar_quoted(&tag).map(Token::Str)
+                    }
+                    else {
+                        Ok(Token::Word(tag))
+                    }
+                },
+                c if c.is_ascii_digit() => Ok(Token::Number(self.number(c))),
+                '.' if next.is_some_and(|n| n.is_ascii_digit()) => Ok(Token::Number(self.number(c))),
+                c if c.is_alphabetic() || c == '_' => Ok(Token::Word(self.word(c))),
+                c => Ok(Token::Symbol(c)),
+            };
+            if self.mark {
+                self.raw.drain(..offset);
+                self.mark = false;
+            }
+            return Some(token);
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for Lexer<I> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Result<Token, Error>> {
+        self.next_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(sql: &str, backslash_escapes: bool) -> Vec<String> {
+        let mut lexer = Lexer::new(sql.chars());
+        lexer.backslash_escapes = backslash_escapes;
+        lexer.filter_map(|t| match t.unwrap() {
+            Token::Str(s) => Some(s),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens: Vec<Token> = Lexer::new("INSERT INTO t -- comment\nVALUES (1.5, /* c */ 0xCAFE, X'00ff');".chars())
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(tokens, vec![Token::Word("INSERT".to_string()), Token::Word("INTO".to_string()),
+            Token::Word("t".to_string()), Token::Word("VALUES".to_string()), Token::Symbol('('),
+            Token::Number("1.5".to_string()), Token::Symbol(','), Token::Hex("CAFE".to_string()), Token::Symbol(','),
+            Token::Hex("00ff".to_string()), Token::Symbol(')'), Token::Symbol(';')]);
+    }
+
+    #[test]
+    fn doubled_quotes() {
+        assert_eq!(strings("'it''s'", false), vec!["it's"]);
+        assert_eq!(strings("'it''s'", true), vec!["it's"]);
+    }
+
+    #[test]
+    fn identifiers() {
+        let tokens: Vec<Token> = Lexer::new("`a b` \"c\" [d]".chars()).map(|t| t.unwrap()).collect();
+        assert_eq!(tokens, vec![Token::Ident("a b".to_string()), Token::Ident("c".to_string()),
+            Token::Ident("d".to_string())]);
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let mut lexer = Lexer::new("/* comment".chars());
+        assert!(lexer.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn mysql_backslash_escapes() {
+        assert_eq!(strings(r"'a\nb\tc', 'it\'s', 'C:\\dir', '\0\Z'", true),
+            vec!["a\nb\tc", "it's", "C:\\dir", "\0\x1a"]);
+    }
+
+    #[test]
+    fn standard_strings_keep_backslashes() {
+        assert_eq!(strings(r"'C:\new\dir\', 'a\nb', '\x00ff'", false), vec![r"C:\new\dir\", r"a\nb", r"\x00ff"]);
+    }
+
+    #[test]
+    fn escape_string_constants() {
+        assert_eq!(strings(r"E'a\nb', e'\\'", false), vec!["a\nb", "\\"]);
+        assert_eq!(strings(r"N'C:\dir'", true), vec![r"C:\dir"]);
+    }
+
+    #[test]
+    fn unterminated_string() {
+        let mut lexer = Lexer::new(r"'C:\dir\'".chars());
+        assert!(lexer.next().unwrap().is_err());
+        let mut lexer = Lexer::new(r"'C:\dir\'".chars());
+        lexer.backslash_escapes = false;
+        assert_eq!(lexer.next().unwrap().unwrap(), Token::Str(r"C:\dir\".to_string()));
+    }
+
+    #[test]
+    fn bits() {
+        let tokens: Vec<Token> = Lexer::new("b'101' B'0' 0b11 b".chars()).map(|t| t.unwrap()).collect();
+        assert_eq!(tokens, vec![Token::Bits("101".to_string()), Token::Bits("0".to_string()),
+            Token::Bits("11".to_string()), Token::Word("b".to_string())]);
+    }
+}
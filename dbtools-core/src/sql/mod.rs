@@ -0,0 +1,740 @@
+use chrono::*;
+use std::cmp;
+use std::convert::TryFrom;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::rc::Rc;
+use rustc_serialize::hex::{FromHex, ToHex};
+use error::Error;
+use flatten::Flatten;
+use value::{FieldSet, Number, Record, Value};
+use util::{self, glob_match, parse_date, Chars, Input};
+
+pub mod lexer;
+pub mod parser;
+pub mod dialect;
+
+use self::parser::{Column, Insert, Key, Literal, Parser, Statement};
+use self::dialect::{Dialect, SqlType};
+
+fn literal_value(l: &Literal) -> Value {
+    match *l {
+        Literal::Null => Value::Null,
+        Literal::Bool(b) => Value::Bool(b),
+        Literal::Number(ref n) => Number::parse(n).map_or_else(|| Value::String(n.clone()), Value::Number),
+        Literal::String(ref s) => {
+            match parse_date(s) {
+                Some(d) => Value::Date(d),
+                None => Value::String(s.clone()),
+            }
+        },
+        Literal::Hex(ref h) => Value::String(format!("0x{}", h)),
+        Literal::Keyword(ref k) => Value::String(k.clone()),
+    }
+}
+
+fn boolean(l: &Literal) -> Option<bool> {
+    match *l {
+        Literal::Bool(b) => Some(b),
+        Literal::Number(ref n) => Some(n != "0"),
+        Literal::String(ref s) => {
+            match s.to_lowercase().as_ref() {
+                "t" | "true" | "y" | "yes" | "1" => Some(true),
+                "f" | "false" | "n" | "no" | "0" => Some(false),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+fn bytes(l: &Literal) -> Option<Vec<u8>> {
+    match *l {
+        Literal::Hex(ref h) => h.from_hex().ok(),
+        // PostgreSQL writes bytea as \x followed by hex digits.
+        Literal::String(ref s) if s.starts_with("\\x") => s[2..].from_hex().ok(),
+        Literal::String(ref s) => Some(s.as_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+fn text(l: &Literal) -> Option<&str> {
+    match *l {
+        Literal::Number(ref n) | Literal::String(ref n) => Some(n),
+        _ => None,
+    }
+}
+
+/// Convert a literal to the type declared for its column, e.g. `BIGINT` or
+/// `DECIMAL(10,2)`. Values which do not fit the type, and values of columns
+/// without a known type, are typed from the literal alone. With `object_ids`,
+/// CHAR(24) columns of hex digits are read as ObjectIds.
+pub fn typed_value(l: &Literal, sql_type: &str, object_ids: bool) -> Value {
+    if *l == Literal::Null {
+        return Value::Null;
+    }
+    let base = sql_type.split(['(', ' ']).next().unwrap_or("");
+    let args = sql_type.find('(').map_or("", |i| sql_type[i + 1..].split(')').next().unwrap_or(""));
+    let typed = match base {
+        "TINYINT" if args == "1" => boolean(l).map(Value::Bool),
+        "INT" | "INTEGER" | "SMALLINT" | "TINYINT" | "MEDIUMINT" | "INT2" | "INT4" | "SERIAL" | "SMALLSERIAL" => {
+            text(l).and_then(|t| match t.parse::<i32>() {
+                Ok(n) => Some(Number::Int32(n)),
+                // INT UNSIGNED can exceed 32 bits.
+                Err(_) => t.parse::<i64>().ok().map(Number::Int64),
+            }).map(Value::Number)
+        },
+        "BIGINT" | "INT8" | "BIGSERIAL" => {
+            text(l).and_then(|t| match t.parse::<i64>() {
+                Ok(n) => Some(Number::Int64(n)),
+                // BIGINT UNSIGNED can exceed 64 bits.
+                Err(_) => t.parse::<u64>().ok().map(|n| Number::Decimal128(n.to_string())),
+            }).map(Value::Number)
+        },
+        "DECIMAL" | "NUMERIC" | "DEC" | "FIXED" => {
+            text(l).and_then(Number::parse).map(|n| Value::Number(Number::Decimal128(n.to_string())))
+        },
+        "FLOAT" | "DOUBLE" | "REAL" | "FLOAT4" | "FLOAT8" => {
+            text(l).and_then(|t| t.parse::<f64>().ok()).map(|n| Value::Number(Number::Double(n)))
+        },
+        "DATE" | "DATETIME" | "DATETIME2" | "SMALLDATETIME" | "TIMESTAMP" | "TIMESTAMPTZ" => {
+            match text(l) {
+                // MySQL zero dates stand for no date.
+                Some(t) if t.starts_with("0000-00-00") => Some(Value::Null),
+                Some(t) => parse_date(t).map(Value::Date),
+                None => None,
+            }
+        },
+        "BOOLEAN" | "BOOL" | "BIT" => boolean(l).map(Value::Bool),
+        "BLOB" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BINARY" | "VARBINARY" | "BYTEA" | "IMAGE" => {
+            bytes(l).map(|b| Value::Binary(0, b))
+        },
+        "CHAR" if object_ids && args == "24" => {
+            match *l {
+                Literal::String(ref s) if s.len() == 24 && s.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                    Some(Value::ObjectId(s.clone()))
+                },
+                _ => None,
+            }
+        },
+        "CHAR" | "VARCHAR" | "NCHAR" | "NVARCHAR" | "TEXT" | "TINYTEXT" | "MEDIUMTEXT" | "LONGTEXT"
+        | "CHARACTER" | "CLOB" | "ENUM" | "SET" | "UUID" | "JSON" => {
+            text(l).map(|t| Value::String(t.to_string()))
+        },
+        _ => None,
+    };
+    typed.unwrap_or_else(|| literal_value(l))
+}
+
+/// Selects tables by name, or by pattern with `*` and `?` wildcards.
+#[derive(Clone, Debug, Default)]
+pub struct TableFilter {
+    /// Tables to read; all if empty.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl TableFilter {
+    /// Build a filter from comma separated lists of names or patterns.
+    pub fn new(include: &str, exclude: &str) -> TableFilter {
+        let list = |l: &str| l.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+        TableFilter { include: list(include), exclude: list(exclude) }
+    }
+
+    pub fn matches(&self, table: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| glob_match(p, table)))
+            && !self.exclude.iter().any(|p| glob_match(p, table))
+    }
+}
+
+/// Map the rows of an INSERT onto the columns of its table. For a table with
+/// a CREATE TABLE, values are put in the declared order and omitted columns
+/// get their constant DEFAULT, or NULL; other tables take the columns of
+/// their INSERTs.
+fn insert_records(i: &Insert, columns: &mut Vec<Column>, declared: bool, object_ids: bool)
+-> Result<Vec<Record>, Error> {
+    let names: Vec<String> = if i.columns.is_empty() {
+        columns.iter().map(|c| c.name.clone()).collect()
+    }
+    else {
+        i.columns.clone()
+    };
+    let mut indices: Vec<usize> = Vec::new();
+    for n in &names {
+        let pos = match columns.iter().position(|c| c.name.eq_ignore_ascii_case(n)) {
+            Some(pos) => pos,
+            None if !declared => {
+                columns.push(Column { name: n.clone(), ..Column::default() });
+                columns.len() - 1
+            },
+            None => {
+                let message = format!("Unknown column '{}' in INSERT INTO '{}'", n, i.table);
+                return Err(Error::schema("", i.line, &message));
+            },
+        };
+        indices.push(pos);
+    }
+
+    let mut records = Vec::new();
+    for row in &i.rows {
+        if row.len() != indices.len() {
+            let message = format!("INSERT INTO '{}' has {} columns but {} values", i.table, indices.len(), row.len());
+            return Err(Error::schema("", i.line, &message));
+        }
+        let mut r = Record::new();
+        for (pos, c) in columns.iter().enumerate() {
+            let v = match indices.iter().position(|p| *p == pos) {
+                Some(k) => typed_value(&row[k], &c.sql_type, object_ids),
+                None if !declared => continue,
+                None => {
+                    match c.default {
+                        // Defaults such as CURRENT_TIMESTAMP have no value in the dump.
+                        None | Some(Literal::Keyword(_)) => Value::Null,
+                        Some(ref d) => typed_value(d, &c.sql_type, object_ids),
+                    }
+                },
+            };
+            r.insert(&c.name, v);
+        }
+        records.push(r);
+    }
+    Ok(records)
+}
+
+/// Reads the rows of a SQL dump into records, each with the name of its table.
+/// Values are typed by their declared column types. The dump is parsed as it
+/// is read, a statement at a time.
+pub struct SqlReader {
+    input: String,
+    statements: Parser<Chars<BufReader<Box<dyn Read>>>>,
+    read_error: Rc<RefCell<Option<io::Error>>>,
+    filter: TableFilter,
+    object_ids: bool,
+    /// Name, columns and whether the columns come from a CREATE TABLE.
+    tables: Vec<(String, Vec<Column>, bool)>,
+    /// Keys of the tables.
+    keys: Vec<Key>,
+    /// Rows of the INSERT being read.
+    rows: VecDeque<(String, Record)>,
+    done: bool,
+}
+
+impl SqlReader {
+    /// Open a dump of a dialect, or of one found from the dump if `None`.
+    pub fn open(input: &Input, filter: &TableFilter, object_ids: bool, dialect: Option<Dialect>)
+    -> Result<SqlReader, Error> {
+        let chars = Chars::new(BufReader::new(input.reader()?));
+        Ok(SqlReader {
+            input: input.name().to_string(),
+            read_error: chars.error(),
+            statements: Parser::new(chars, dialect),
+            filter: filter.clone(),
+            object_ids,
+            tables: Vec::new(),
+            keys: Vec::new(),
+            rows: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Names of the tables read so far, in the order they appear in the dump.
+    pub fn tables(&self) -> Vec<String> {
+        self.tables.iter().map(|t| t.0.clone()).collect()
+    }
+
+    pub fn columns(&self, table: &str) -> &[Column] {
+        self.tables.iter().find(|t| t.0 == table).map_or(&[], |t| &t.1)
+    }
+
+    /// The primary key and indexes of a table, as declared so far.
+    pub fn keys(&self, table: &str) -> Vec<Key> {
+        self.keys.iter().filter(|k| k.table == table).cloned().collect()
+    }
+
+    /// Read statements until one has rows.
+    fn read_rows(&mut self) -> Result<(), Error> {
+        while self.rows.is_empty() {
+            let statement = match self.statements.next() {
+                Some(s) => s?,
+                None => {
+                    if let Some(e) = self.read_error.borrow_mut().take() {
+                        return Err(Error::io(&self.input, e));
+                    }
+                    self.done = true;
+                    return Ok(());
+                },
+            };
+            match statement {
+                Statement::CreateTable(t) => {
+                    if !self.filter.matches(&t.name) {
+                        continue;
+                    }
+                    self.keys.retain(|k| k.table != t.name);
+                    self.keys.extend(t.keys.iter().cloned());
+                    match self.tables.iter().position(|t2| t2.0 == t.name) {
+                        Some(pos) => {
+                            self.tables[pos].1 = t.columns;
+                            self.tables[pos].2 = true;
+                        },
+                        None => self.tables.push((t.name, t.columns, true)),
+                    }
+                },
+                Statement::Key(k) => {
+                    if self.filter.matches(&k.table) {
+                        self.keys.push(k);
+                    }
+                },
+                Statement::Insert(i) => {
+                    if !self.filter.matches(&i.table) {
+                        continue;
+                    }
+                    let pos = match self.tables.iter().position(|t| t.0 == i.table) {
+                        Some(pos) => pos,
+                        None => {
+                            self.tables.push((i.table.clone(), Vec::new(), false));
+                            self.tables.len() - 1
+                        },
+                    };
+                    let declared = self.tables[pos].2;
+                    let records = insert_records(&i, &mut self.tables[pos].1, declared, self.object_ids)
+                        .map_err(|e| e.in_record(self.statements.text().as_bytes()))?;
+                    for r in records {
+                        self.rows.push_back((i.table.clone(), r));
+                    }
+                },
+                Statement::Other => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SqlReader {
+    type Item = Result<(String, Record), Error>;
+
+    /// The next row, or the error which ends the dump.
+    fn next(&mut self) -> Option<Result<(String, Record), Error>> {
+        if self.done {
+            return None;
+        }
+        if let Err(e) = self.read_rows() {
+            // Reading can carry on past a bad statement, but not other errors.
+            if !matches!(e, Error::BadRecord { .. }) {
+                self.done = true;
+            }
+            return Some(Err(e.in_file(&self.input)));
+        }
+        self.rows.pop_front().map(Ok)
+    }
+}
+
+/// Longest VARCHAR a column is given; longer text is TEXT.
+const MAX_VARCHAR: usize = 255;
+
+/// The kinds of value a column may hold, each of which has its own type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Text,
+    Date,
+    Number,
+    Bool,
+    Binary,
+    Timestamp,
+}
+
+impl Kind {
+    fn of(v: &Value) -> Kind {
+        match *v {
+            Value::Date(_) => Kind::Date,
+            Value::Number(_) => Kind::Number,
+            Value::Bool(_) => Kind::Bool,
+            Value::Binary(_, _) => Kind::Binary,
+            Value::Timestamp(_, _) => Kind::Timestamp,
+            _ => Kind::Text,
+        }
+    }
+}
+
+/// A scalar value as text, as it is written to a text column.
+fn value_text(v: &Value) -> String {
+    match *v {
+        Value::ObjectId(ref s) | Value::String(ref s) => s.clone(),
+        Value::Date(ref t) => util::format_sql_timestamp(t),
+        Value::Number(ref n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Binary(_, ref bytes) => bytes.to_hex(),
+        Value::Regex(ref pattern, ref options) => format!("/{}/{}", pattern, options),
+        Value::Timestamp(t, i) => ((t as u64) << 32 | i as u64).to_string(),
+        Value::MinKey => "MinKey".to_string(),
+        Value::MaxKey => "MaxKey".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// What the values of a column seen so far say about its type.
+struct ColumnStats {
+    /// Kind of the values which are not null, if any are.
+    kind: Option<Kind>,
+    /// Values are of more than one kind, so the column is text.
+    mixed: bool,
+    /// Length in characters of the longest value as text.
+    length: usize,
+    /// All numbers are integers, and all fit 32 bits.
+    integers: bool,
+    int32: bool,
+    /// Widest integer part and fraction of the numbers, unless one has no
+    /// fixed precision.
+    digits: usize,
+    scale: usize,
+    double: bool,
+}
+
+impl ColumnStats {
+    fn new() -> ColumnStats {
+        ColumnStats {
+            kind: None,
+            mixed: false,
+            length: 0,
+            integers: true,
+            int32: true,
+            digits: 0,
+            scale: 0,
+            double: false,
+        }
+    }
+
+    fn add(&mut self, v: &Value) {
+        if v.is_null() {
+            return;
+        }
+        let kind = Kind::of(v);
+        match self.kind {
+            None => self.kind = Some(kind),
+            Some(k) if k != kind => self.mixed = true,
+            Some(_) => (),
+        }
+        self.length = cmp::max(self.length, value_text(v).chars().count());
+        if let Value::Number(ref n) = *v {
+            match *n {
+                Number::Int(i) => self.int32 = self.int32 && i32::try_from(i).is_ok(),
+                Number::Int32(_) => (),
+                Number::Int64(_) => self.int32 = false,
+                _ => self.integers = false,
+            }
+            match n.precision() {
+                Some((p, s)) => {
+                    self.digits = cmp::max(self.digits, p - s);
+                    self.scale = cmp::max(self.scale, s);
+                },
+                None => self.double = true,
+            }
+        }
+    }
+
+    /// INTEGER or BIGINT for integers, DECIMAL(p,s) wide enough for every
+    /// value, or DOUBLE for floating point and numbers too wide for DECIMAL.
+    fn number_type(&self) -> SqlType {
+        if self.integers {
+            return if self.int32 { SqlType::Integer } else { SqlType::BigInt };
+        }
+        if self.double || self.digits + self.scale > 38 {
+            return SqlType::Double;
+        }
+        SqlType::Decimal(cmp::max(self.digits + self.scale, 1), self.scale)
+    }
+
+    /// VARCHAR as long as the longest value, or TEXT for long values and
+    /// columns with none.
+    fn text_type(&self) -> SqlType {
+        if self.kind.is_none() || self.length > MAX_VARCHAR {
+            return SqlType::Text;
+        }
+        SqlType::Varchar(cmp::max(self.length, 1))
+    }
+
+    /// The type of the values' kind, or text for values of several kinds.
+    fn column_type(&self) -> SqlType {
+        if self.mixed {
+            return self.text_type();
+        }
+        match self.kind {
+            Some(Kind::Date) => SqlType::Timestamp,
+            Some(Kind::Number) => self.number_type(),
+            Some(Kind::Bool) => SqlType::Boolean,
+            Some(Kind::Binary) => SqlType::Blob,
+            Some(Kind::Timestamp) => SqlType::BigInt,
+            Some(Kind::Text) | None => self.text_type(),
+        }
+    }
+}
+
+/// The columns of a table being found from its records, one at a time.
+#[derive(Default)]
+pub struct Schema {
+    fields: FieldSet,
+    stats: HashMap<String, ColumnStats>,
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    pub fn add(&mut self, r: &Record) {
+        self.fields.add(r);
+        for (k, v) in r {
+            if v.is_scalar() {
+                self.stats.entry(k.clone()).or_insert_with(ColumnStats::new).add(v);
+            }
+        }
+    }
+
+    /// Add a column known without values, e.g. one declared by a table
+    /// without rows, unless it is already there.
+    pub fn declare(&mut self, column: &str) {
+        if self.fields.fields().iter().any(|f| f == column) {
+            return;
+        }
+        let mut r = Record::new();
+        r.insert(column, Value::Null);
+        self.add(&r);
+    }
+
+    /// Columns and their types for the scalar fields added.
+    pub fn columns(&self) -> Vec<(String, SqlType)> {
+        self.fields.fields().iter()
+            .filter_map(|k| self.stats.get(k).map(|c| (k.clone(), c.column_type())))
+            .collect()
+    }
+}
+
+/// Writes records as a SQL dump of a table. There is a column for every field
+/// found in any record, typed from its values, and missing fields are inserted
+/// as NULL. Embedded documents and arrays are flattened into dotted columns,
+/// as for CSV. The columns come from a first pass over the records, `schema()`,
+/// and the rows are written as they are read on a second.
+#[derive(Default)]
+pub struct SqlWriter {
+    /// Program signature written in the header comments.
+    pub signature: String,
+    /// Description of the input, e.g. "MongoDB collection" or "CSV file".
+    pub origin: String,
+    /// Database to `USE`, if not empty.
+    pub db: String,
+    pub comments: bool,
+    pub dialect: Dialect,
+    pub flatten: Flatten,
+}
+
+impl SqlWriter {
+    /// Columns and their types for the flattened fields of the records.
+    pub fn schema<I: IntoIterator<Item = Result<Record, Error>>>(&self, records: I)
+    -> Result<Vec<(String, SqlType)>, Error> {
+        let mut schema = Schema::new();
+        for r in records {
+            schema.add(&self.record(&r?));
+        }
+        Ok(schema.columns())
+    }
+
+    /// A record as the row of a table: its fields flattened.
+    pub fn record(&self, r: &Record) -> Record {
+        self.flatten.record(r, false)
+    }
+
+    /// A value as a literal for a column of a type; values of text columns
+    /// are strings, whatever their kind.
+    fn format_value(&self, v: &Value, column: SqlType) -> String {
+        let d = self.dialect;
+        let is_text = matches!(column, SqlType::Char(_) | SqlType::Varchar(_) | SqlType::Text);
+        match *v {
+            Value::Null => "NULL".to_string(),
+            _ if is_text && v.is_scalar() => d.string(&value_text(v)),
+            Value::Date(ref t) if column == SqlType::Date => d.string(&t.format("%Y-%m-%d").to_string()),
+            Value::ObjectId(ref s) | Value::String(ref s) => d.string(s),
+            Value::Date(ref t) => d.timestamp(t),
+            // NaN and the infinities, which SQL has no literals for, are strings.
+            Value::Number(ref n) if !n.is_literal() => d.string(&n.to_string()),
+            Value::Number(ref n) => n.to_string(),
+            Value::Bool(b) => d.boolean(b),
+            Value::Binary(_, ref bytes) => d.binary(bytes),
+            Value::Regex(ref pattern, ref options) => d.string(&format!("/{}/{}", pattern, options)),
+            Value::Timestamp(t, i) => ((t as u64) << 32 | i as u64).to_string(),
+            Value::MinKey => d.string("MinKey"),
+            Value::MaxKey => d.string("MaxKey"),
+            _ => "NULL".to_string(),
+        }
+    }
+
+    /// Start the dump with its comments and the definitions of its tables.
+    pub fn create(&self, tables: Vec<SqlTable>, input: &str, output: &str) -> Result<SqlOutput<'_>, Error> {
+        let mut out = SqlOutput {
+            writer: self,
+            path: output.to_string(),
+            tables,
+            out: BufWriter::new(util::create(output)?),
+        };
+        out.header(input).map_err(|e| Error::io(output, e))?;
+        Ok(out)
+    }
+
+    /// Write a dump of the records of a single table.
+    pub fn write<I: IntoIterator<Item = Result<Record, Error>>>(&self, table: SqlTable, records: I, input: &str,
+    output: &str) -> Result<(), Error> {
+        let name = table.name.clone();
+        let mut out = self.create(vec![table], input, output)?;
+        for r in records {
+            out.write(&name, &r?)?;
+        }
+        out.finish()
+    }
+}
+
+/// An index to create on a table, e.g. from a MongoDB collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SqlIndex {
+    pub name: String,
+    /// Columns, each with whether it is in descending order.
+    pub columns: Vec<(String, bool)>,
+    pub unique: bool,
+}
+
+/// A table to write: its columns and their types, and the columns of its
+/// primary key and other indexes, if it has any.
+#[derive(Clone, Debug, Default)]
+pub struct SqlTable {
+    pub name: String,
+    pub columns: Vec<(String, SqlType)>,
+    /// Columns declared NOT NULL, besides those of the primary key.
+    pub not_null: Vec<String>,
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<SqlIndex>,
+}
+
+impl SqlTable {
+    pub fn new(name: &str, columns: Vec<(String, SqlType)>) -> SqlTable {
+        SqlTable { name: name.to_string(), columns, ..SqlTable::default() }
+    }
+}
+
+/// A SQL dump being written, one INSERT per record. The indexes of its tables
+/// are created at the end, after their rows.
+pub struct SqlOutput<'a> {
+    writer: &'a SqlWriter,
+    path: String,
+    tables: Vec<SqlTable>,
+    out: BufWriter<Box<dyn Write>>,
+}
+
+impl<'a> SqlOutput<'a> {
+    fn header(&mut self, input: &str) -> io::Result<()> {
+        let w = self.writer;
+        let d = w.dialect;
+        if w.comments {
+            let timestamp: DateTime<Local> = Local::now();
+            let output = if util::is_stdio(&self.path) { "stdout" } else { &self.path };
+            let names: Vec<&str> = self.tables.iter().map(|t| t.name.as_str()).collect();
+            writeln!(self.out, "-- SQL table dump from {}: {} ({} -> {})", w.origin, names.join(", "), input, output)?;
+            writeln!(self.out, "-- Generated by: {}", w.signature)?;
+            writeln!(self.out, "-- Generated at: {}", timestamp)?;
+            writeln!(self.out)?;
+        }
+        if let Some(begin) = d.begin() {
+            writeln!(self.out, "{}", begin)?;
+        }
+        if !w.db.is_empty() {
+            if let Some(u) = d.use_database(&w.db) {
+                writeln!(self.out, "{}", u)?;
+            }
+        }
+        for (i, t) in self.tables.iter().enumerate() {
+            if i > 0 {
+                writeln!(self.out)?;
+            }
+            writeln!(self.out, "DROP TABLE IF EXISTS {};", d.quote(&t.name))?;
+            // Columns of the primary key are NOT NULL, which SQL Server needs.
+            let mut columns: Vec<String> = t.columns.iter()
+                .map(|&(ref k, ty)| {
+                    let null = if t.primary_key.contains(k) || t.not_null.contains(k) { " NOT NULL" } else { "" };
+                    format!("{} {}{}", d.quote(k), d.type_name(ty), null)
+                })
+                .collect();
+            if !t.primary_key.is_empty() {
+                let key: Vec<String> = t.primary_key.iter().map(|k| d.key_column(t, k)).collect();
+                columns.push(format!("PRIMARY KEY ({})", key.join(", ")));
+            }
+            writeln!(self.out, "{}", d.create_table(&t.name))?;
+            writeln!(self.out, "{});", columns.join(",\n"))?;
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, table: &str, r: &Record) -> io::Result<()> {
+        let d = self.writer.dialect;
+        let t = match self.tables.iter().find(|t| t.name == table) {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        let r = self.writer.record(r);
+        let values: Vec<String> = t.columns.iter()
+            .map(|c| self.writer.format_value(r.get(&c.0).filter(|v| v.is_scalar()).unwrap_or(&Value::Null), c.1))
+            .collect();
+        writeln!(self.out)?;
+        writeln!(self.out, "INSERT INTO {} VALUES (", d.quote(table))?;
+        writeln!(self.out, "{});", values.join(",\n"))
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        let d = self.writer.dialect;
+        for t in &self.tables {
+            for index in &t.indexes {
+                writeln!(self.out)?;
+                writeln!(self.out, "{}", d.create_index(t, index))?;
+            }
+        }
+        if let Some(end) = d.end() {
+            writeln!(self.out)?;
+            writeln!(self.out, "{}", end)?;
+        }
+        Ok(())
+    }
+
+    /// Write a row of one of the tables.
+    pub fn write(&mut self, table: &str, r: &Record) -> Result<(), Error> {
+        self.insert(table, r).map_err(|e| Error::io(&self.path, e))
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.end().and_then(|_| self.out.flush()).map_err(|e| Error::io(&self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_documents_become_columns() {
+        let mut address = Record::new();
+        address.insert("city", Value::String("Oslo".to_string()));
+        let mut r = Record::new();
+        r.insert("_id", Value::Number(Number::Int(1)));
+        r.insert("address", Value::Document(address));
+        r.insert("tags", Value::Array(vec![Value::String("a".to_string()), Value::String("bc".to_string())]));
+        r.insert("none", Value::Array(Vec::new()));
+        let columns = SqlWriter::default().schema(vec![Ok(r)]).unwrap();
+        assert_eq!(columns, vec![("_id".to_string(), SqlType::Integer), ("address.city".to_string(), SqlType::Varchar(4)),
+            ("tags.0".to_string(), SqlType::Varchar(1)), ("tags.1".to_string(), SqlType::Varchar(2)),
+            ("none".to_string(), SqlType::Varchar(2))]);
+    }
+
+    #[test]
+    fn numbers_which_are_not_literals_are_strings() {
+        let w = SqlWriter::default();
+        assert_eq!(w.format_value(&Value::Number(Number::Decimal128("-1.50".to_string())), SqlType::Decimal(3, 2)), "-1.50");
+        assert_eq!(w.format_value(&Value::Number(Number::Decimal128("NaN".to_string())), SqlType::Double), "'NaN'");
+        assert_eq!(w.format_value(&Value::Number(Number::Double(f64::INFINITY)), SqlType::Double), "'Infinity'");
+        assert_eq!(w.format_value(&Value::Number(Number::Decimal("1); --".to_string())), SqlType::Double), "'1); --'");
+    }
+}
@@ -0,0 +1,900 @@
+use sql::dialect::Dialect;
+use sql::lexer::{Lexer, Token};
+use error::Error;
+
+/// A literal value as written in a SQL statement.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Null,
+    Bool(bool),
+    /// Numeric literal in its original text.
+    Number(String),
+    String(String),
+    /// Hex digits of a binary literal.
+    Hex(String),
+    /// Any other bare word, e.g. `CURRENT_TIMESTAMP`.
+    Keyword(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Column {
+    pub name: String,
+    /// Declared type in upper case, e.g. `VARCHAR(30)` or `DECIMAL(10,2) UNSIGNED`.
+    pub sql_type: String,
+    /// Declared DEFAULT; a keyword for defaults such as CURRENT_TIMESTAMP.
+    pub default: Option<Literal>,
+    pub not_null: bool,
+}
+
+/// A primary key, unique key or index of a table, declared in its CREATE
+/// TABLE or by a later CREATE INDEX or ALTER TABLE.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Key {
+    pub table: String,
+    /// Name of the index; empty if it has none, e.g. a primary key.
+    pub name: String,
+    /// Columns, each with whether it is in descending order.
+    pub columns: Vec<(String, bool)>,
+    pub primary: bool,
+    pub unique: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateTable {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub keys: Vec<Key>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    /// Explicit column list, empty if the statement has none.
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Literal>>,
+    /// Line the statement starts on.
+    pub line: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    CreateTable(CreateTable),
+    Insert(Insert),
+    /// CREATE INDEX, or a key added by ALTER TABLE.
+    Key(Key),
+    /// Any statement the converters have no use for.
+    Other,
+}
+
+fn is_word(t: Option<&Token>, word: &str) -> bool {
+    match t {
+        Some(Token::Word(w)) => w.eq_ignore_ascii_case(word),
+        _ => false,
+    }
+}
+
+fn is_symbol(t: Option<&Token>, symbol: char) -> bool {
+    t == Some(&Token::Symbol(symbol))
+}
+
+/// Rebuild a column's declared type from the tokens following its name.
+fn column_type(tokens: &[Token]) -> String {
+    let mut sql_type = String::new();
+    for (i, t) in tokens.iter().enumerate() {
+        match *t {
+            Token::Word(ref w) => {
+                if ["NOT", "NULL", "DEFAULT", "PRIMARY", "AUTO_INCREMENT", "COMMENT", "COLLATE",
+                    "REFERENCES", "UNIQUE", "KEY", "CHECK", "GENERATED", "ON"]
+                    .iter().any(|k| w.eq_ignore_ascii_case(k)) {
+                    break;
+                }
+                // CHARACTER SET, but not CHARACTER VARYING.
+                if w.eq_ignore_ascii_case("CHARACTER") && is_word(tokens.get(i + 1), "SET") {
+                    break;
+                }
+                if !sql_type.is_empty() && !sql_type.ends_with('(') && !sql_type.ends_with(',') {
+                    sql_type.push(' ');
+                }
+                sql_type.push_str(&w.to_uppercase());
+            },
+            Token::Number(ref n) => sql_type.push_str(n),
+            Token::Str(ref s) => sql_type.push_str(&format!("'{}'", s.replace("'", "''"))),
+            Token::Symbol(c) => sql_type.push(c),
+            _ => (),
+        }
+    }
+    sql_type
+}
+
+/// True if the tokens have the words given in a row, e.g. `NOT NULL`.
+fn has_words(tokens: &[Token], words: &[&str]) -> bool {
+    (0..tokens.len()).any(|i| words.iter().enumerate().all(|(k, w)| is_word(tokens.get(i + k), w)))
+}
+
+/// The columns of a key, in parentheses from the first token, e.g.
+/// `(name(10), age DESC)`. Prefix lengths and collations are left out.
+fn key_columns(tokens: &[Token]) -> Vec<(String, bool)> {
+    let mut columns: Vec<(String, bool)> = Vec::new();
+    let mut depth = 0;
+    let mut first = true;
+    for t in tokens {
+        match *t {
+            Token::Symbol('(') => depth += 1,
+            Token::Symbol(')') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            },
+            Token::Symbol(',') if depth == 1 => first = true,
+            Token::Word(ref w) | Token::Ident(ref w) if depth == 1 => {
+                if first {
+                    columns.push((w.clone(), false));
+                    first = false;
+                }
+                else if w.eq_ignore_ascii_case("DESC") {
+                    if let Some(c) = columns.last_mut() {
+                        c.1 = true;
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+    columns
+}
+
+/// A key declared by the tokens of a table constraint, e.g.
+/// `CONSTRAINT pk PRIMARY KEY (id)`, `UNIQUE KEY email (email)` or
+/// `KEY name (name)`. Foreign keys, checks and full-text indexes are not.
+fn table_key(table: &str, tokens: &[Token]) -> Option<Key> {
+    let mut i = 0;
+    let mut name = String::new();
+    if is_word(tokens.first(), "CONSTRAINT") {
+        if let Some(Token::Word(ref n)) | Some(Token::Ident(ref n)) = tokens.get(1) {
+            name = n.clone();
+        }
+        i = 2;
+    }
+    let mut key = Key { table: table.to_string(), ..Key::default() };
+    if is_word(tokens.get(i), "PRIMARY") {
+        key.primary = true;
+    }
+    else if is_word(tokens.get(i), "UNIQUE") {
+        key.unique = true;
+    }
+    else if !is_word(tokens.get(i), "KEY") && !is_word(tokens.get(i), "INDEX") {
+        return None;
+    }
+    i += 1;
+    while is_word(tokens.get(i), "KEY") || is_word(tokens.get(i), "INDEX") {
+        i += 1;
+    }
+    if let Some(Token::Word(ref n)) | Some(Token::Ident(ref n)) = tokens.get(i) {
+        name = n.clone();
+        i += 1;
+    }
+    let start = tokens[i..].iter().position(|t| is_symbol(Some(t), '('))? + i;
+    key.name = name;
+    key.columns = key_columns(&tokens[start..]);
+    if key.columns.is_empty() { None } else { Some(key) }
+}
+
+/// The integer of the digits of a bit literal, e.g. of a BIT column as
+/// mysqldump writes it, if it has no more than 64.
+fn bits(digits: &str) -> Option<Literal> {
+    if digits.is_empty() {
+        return Some(Literal::Number("0".to_string()));
+    }
+    u64::from_str_radix(digits, 2).ok().map(|n| Literal::Number(n.to_string()))
+}
+
+/// The value after DEFAULT in a column definition, if any.
+fn column_default(tokens: &[Token]) -> Option<Literal> {
+    let i = tokens.iter().position(|t| is_word(Some(t), "DEFAULT"))?;
+    match *tokens.get(i + 1)? {
+        Token::Str(ref s) => Some(Literal::String(s.clone())),
+        Token::Number(ref n) => Some(Literal::Number(n.clone())),
+        Token::Hex(ref h) => Some(Literal::Hex(h.clone())),
+        Token::Bits(ref b) => bits(b),
+        Token::Symbol('-') => {
+            match *tokens.get(i + 2)? {
+                Token::Number(ref n) => Some(Literal::Number(format!("-{}", n))),
+                _ => None,
+            }
+        },
+        Token::Word(ref w) => {
+            match w.to_uppercase().as_ref() {
+                "NULL" => Some(Literal::Null),
+                "TRUE" => Some(Literal::Bool(true)),
+                "FALSE" => Some(Literal::Bool(false)),
+                w => Some(Literal::Keyword(w.to_string())),
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Decode a line of PostgreSQL COPY text data: tab separated, `\N` for NULL
+/// and backslash escapes.
+fn copy_row(line: &str) -> Vec<Literal> {
+    line.split('\t').map(|f| {
+        if f == "\\N" {
+            return Literal::Null;
+        }
+        let mut s = String::new();
+        let mut chars = f.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                s.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('b') => s.push('\x08'),
+                Some('f') => s.push('\x0c'),
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('v') => s.push('\x0b'),
+                Some(d) if d.is_digit(8) || d == 'x' => {
+                    let (radix, max) = if d == 'x' { (16, 2) } else { (8, 3) };
+                    let mut code = if d == 'x' { String::new() } else { d.to_string() };
+                    while code.len() < max && chars.peek().is_some_and(|c| c.is_digit(radix)) {
+                        code.push(chars.next().unwrap());
+                    }
+                    match u32::from_str_radix(&code, radix).ok().and_then(std::char::from_u32) {
+                        Some(c) => s.push(c),
+                        None => s.push(d),
+                    }
+                },
+                Some(c) => s.push(c),
+                None => s.push('\\'),
+            }
+        }
+        Literal::String(s)
+    }).collect()
+}
+
+/// Parses the CREATE TABLE and INSERT statements out of a SQL dump. The rows
+/// of a PostgreSQL `COPY ... FROM stdin` block are returned as one INSERT each.
+///
+/// Backslashes in strings are escapes in MySQL dumps only. Unless the dialect
+/// is given, a dump is taken to be MySQL's until it says otherwise, with
+/// `SET standard_conforming_strings` as pg_dump writes, or with the `PRAGMA`
+/// and `BEGIN TRANSACTION` of sqlite3.
+pub struct Parser<I: Iterator<Item = char>> {
+    lexer: Lexer<I>,
+    peeked: Option<Token>,
+    /// Table and columns of the COPY block being read.
+    copy: Option<(String, Vec<String>)>,
+    /// The last token read ended a statement.
+    ended: bool,
+    /// The dialect is found from the dump.
+    detect: bool,
+}
+
+impl<I: Iterator<Item = char>> Parser<I> {
+    pub fn new(chars: I, dialect: Option<Dialect>) -> Parser<I> {
+        let mut lexer = Lexer::new(chars);
+        lexer.backslash_escapes = dialect.is_none_or(|d| d.backslash_escapes());
+        Parser { lexer, peeked: None, copy: None, ended: false, detect: dialect.is_none() }
+    }
+
+    /// Text of the last statement, or line of COPY data, as it was read.
+    pub fn text(&self) -> &str {
+        self.lexer.raw().trim_end_matches(['\r', '\n'])
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        let (line, column) = self.lexer.position();
+        Error::parse(line, column, msg)
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, Error> {
+        if self.peeked.is_none() {
+            self.peeked = match self.lexer.next_token() {
+                Some(t) => Some(t?),
+                None => None,
+            };
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn advance(&mut self) -> Result<Option<Token>, Error> {
+        self.peek()?;
+        self.ended = is_symbol(self.peeked.as_ref(), ';');
+        Ok(self.peeked.take())
+    }
+
+    fn expect_next(&mut self) -> Result<Token, Error> {
+        match self.advance()? {
+            Some(t) => Ok(t),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn accept_word(&mut self, word: &str) -> Result<bool, Error> {
+        if is_word(self.peek()?, word) {
+            self.advance()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn accept_symbol(&mut self, symbol: char) -> Result<bool, Error> {
+        if is_symbol(self.peek()?, symbol) {
+            self.advance()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), Error> {
+        if self.accept_symbol(symbol)? {
+            return Ok(());
+        }
+        Err(self.error(&format!("Expected '{}'", symbol)))
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self.expect_next()? {
+            Token::Word(w) | Token::Ident(w) => Ok(w),
+            t => Err(self.error(&format!("Expected identifier, found {:?}", t))),
+        }
+    }
+
+    /// A possibly qualified name such as `db`.`table`; only the last part is kept.
+    fn name(&mut self) -> Result<String, Error> {
+        let mut name = self.identifier()?;
+        while self.accept_symbol('.')? {
+            name = self.identifier()?;
+        }
+        Ok(name)
+    }
+
+    /// Skip tokens up to and including the end of the current statement.
+    fn skip_statement(&mut self) -> Result<(), Error> {
+        let mut depth = 0;
+        while let Some(t) = self.advance()? {
+            match t {
+                Token::Symbol('(') => depth += 1,
+                Token::Symbol(')') => depth -= 1,
+                Token::Symbol(';') if depth <= 0 => break,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the text of tokens up to a top level ',' or ')' without consuming it.
+    fn definition(&mut self) -> Result<Vec<Token>, Error> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+        loop {
+            match self.peek()? {
+                Some(&Token::Symbol(',')) | Some(&Token::Symbol(')')) if depth == 0 => break,
+                Some(&Token::Symbol('(')) => depth += 1,
+                Some(&Token::Symbol(')')) => depth -= 1,
+                None => return Err(self.error("Unexpected end of input")),
+                _ => (),
+            }
+            tokens.push(self.expect_next()?);
+        }
+        Ok(tokens)
+    }
+
+    fn create_table(&mut self) -> Result<Statement, Error> {
+        let unique = self.accept_word("UNIQUE")?;
+        if self.accept_word("INDEX")? {
+            return self.create_index(unique);
+        }
+        if !self.accept_word("TABLE")? {
+            self.skip_statement()?;
+            return Ok(Statement::Other);
+        }
+        if self.accept_word("IF")? {
+            self.accept_word("NOT")?;
+            self.accept_word("EXISTS")?;
+        }
+        let name = self.name()?;
+        if !self.accept_symbol('(')? {
+            // CREATE TABLE ... AS SELECT or LIKE.
+            self.skip_statement()?;
+            return Ok(Statement::Other);
+        }
+
+        let mut columns = Vec::new();
+        let mut keys = Vec::new();
+        loop {
+            let constraint = {
+                let t = self.peek()?;
+                ["PRIMARY", "KEY", "INDEX", "UNIQUE", "CONSTRAINT", "FOREIGN", "CHECK", "FULLTEXT", "SPATIAL"]
+                    .iter().any(|k| is_word(t, k))
+            };
+            let tokens = self.definition()?;
+            if constraint {
+                keys.extend(table_key(&name, &tokens));
+            }
+            else if !tokens.is_empty() {
+                let column = match tokens[0] {
+                    Token::Word(ref w) | Token::Ident(ref w) => w.clone(),
+                    ref t => return Err(self.error(&format!("Expected column name, found {:?}", t))),
+                };
+                let definition = &tokens[1..];
+                let primary = has_words(definition, &["PRIMARY", "KEY"]);
+                if primary || has_words(definition, &["UNIQUE"]) {
+                    keys.push(Key {
+                        table: name.clone(),
+                        columns: vec![(column.clone(), false)],
+                        primary,
+                        unique: !primary,
+                        ..Key::default()
+                    });
+                }
+                columns.push(Column {
+                    sql_type: column_type(definition),
+                    default: column_default(definition),
+                    not_null: primary || has_words(definition, &["NOT", "NULL"]),
+                    name: column,
+                });
+            }
+            if !self.accept_symbol(',')? {
+                break;
+            }
+        }
+        self.expect_symbol(')')?;
+        self.skip_statement()?;
+        Ok(Statement::CreateTable(CreateTable { name, columns, keys }))
+    }
+
+    /// `CREATE [UNIQUE] INDEX name ON table (columns)`, after CREATE and
+    /// UNIQUE.
+    fn create_index(&mut self, unique: bool) -> Result<Statement, Error> {
+        if self.accept_word("IF")? {
+            self.accept_word("NOT")?;
+            self.accept_word("EXISTS")?;
+        }
+        let name = self.name()?;
+        if !self.accept_word("ON")? {
+            self.skip_statement()?;
+            return Ok(Statement::Other);
+        }
+        self.accept_word("ONLY")?;
+        let table = self.name()?;
+        let mut tokens = Vec::new();
+        while !self.ended {
+            match self.advance()? {
+                Some(t) => tokens.push(t),
+                None => break,
+            }
+        }
+        let start = match tokens.iter().position(|t| is_symbol(Some(t), '(')) {
+            Some(start) => start,
+            None => return Ok(Statement::Other),
+        };
+        let columns = key_columns(&tokens[start..]);
+        Ok(Statement::Key(Key { table, name, columns, primary: false, unique }))
+    }
+
+    /// `ALTER TABLE table ADD [CONSTRAINT name] PRIMARY KEY|UNIQUE (columns)`,
+    /// as pg_dump writes after the data; other changes are skipped.
+    fn alter_table(&mut self) -> Result<Statement, Error> {
+        if !self.accept_word("TABLE")? {
+            self.skip_statement()?;
+            return Ok(Statement::Other);
+        }
+        self.accept_word("ONLY")?;
+        if self.accept_word("IF")? {
+            self.accept_word("EXISTS")?;
+        }
+        self.accept_word("ONLY")?;
+        let table = self.name()?;
+        if !self.accept_word("ADD")? {
+            self.skip_statement()?;
+            return Ok(Statement::Other);
+        }
+        let mut tokens = Vec::new();
+        while !self.ended {
+            match self.advance()? {
+                Some(t) => tokens.push(t),
+                None => break,
+            }
+        }
+        match table_key(&table, &tokens) {
+            Some(key) if key.primary || key.unique => Ok(Statement::Key(key)),
+            _ => Ok(Statement::Other),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Literal, Error> {
+        match self.expect_next()? {
+            Token::Str(s) | Token::Ident(s) => Ok(Literal::String(s)),
+            Token::Number(n) => Ok(Literal::Number(n)),
+            Token::Hex(h) => Ok(Literal::Hex(h)),
+            Token::Bits(b) => bits(&b).ok_or_else(|| self.error(&format!("Invalid bit literal '{}'", b))),
+            Token::Symbol('-') => {
+                match self.expect_next()? {
+                    Token::Number(n) => Ok(Literal::Number(format!("-{}", n))),
+                    t => Err(self.error(&format!("Expected number, found {:?}", t))),
+                }
+            },
+            Token::Symbol('+') => self.literal(),
+            Token::Word(w) => {
+                match w.to_uppercase().as_ref() {
+                    "NULL" => Ok(Literal::Null),
+                    "TRUE" => Ok(Literal::Bool(true)),
+                    "FALSE" => Ok(Literal::Bool(false)),
+                    // Character set introducers, e.g. _binary 'abc' from mysqldump.
+                    _ if w.starts_with('_')
+                        && matches!(self.peek()?, Some(&Token::Str(_)) | Some(&Token::Hex(_)) | Some(&Token::Bits(_))) => {
+                        self.literal()
+                    },
+                    // sqlite3 writes strings with newlines as replace('a\nb','\n',char(10)).
+                    "REPLACE" | "CHAR" if is_symbol(self.peek()?, '(') => self.function(&w.to_uppercase()),
+                    _ => {
+                        // Function calls such as NOW() are kept by name.
+                        if self.accept_symbol('(')? {
+                            let mut depth = 1;
+                            while depth > 0 {
+                                match self.expect_next()? {
+                                    Token::Symbol('(') => depth += 1,
+                                    Token::Symbol(')') => depth -= 1,
+                                    _ => (),
+                                }
+                            }
+                        }
+                        Ok(Literal::Keyword(w.to_uppercase()))
+                    },
+                }
+            },
+            t => Err(self.error(&format!("Expected value, found {:?}", t))),
+        }
+    }
+
+    /// Evaluate REPLACE() and CHAR() of literal arguments.
+    fn function(&mut self, name: &str) -> Result<Literal, Error> {
+        self.expect_symbol('(')?;
+        let mut args = Vec::new();
+        if !self.accept_symbol(')')? {
+            loop {
+                args.push(self.value()?);
+                if !self.accept_symbol(',')? {
+                    break;
+                }
+            }
+            self.expect_symbol(')')?;
+        }
+        let string = |l: &Literal| match *l {
+            Literal::String(ref s) => Some(s.clone()),
+            _ => None,
+        };
+        let result = match name {
+            "CHAR" => args.iter().map(|a| match *a {
+                Literal::Number(ref n) => n.parse::<u32>().ok().and_then(std::char::from_u32),
+                _ => None,
+            }).collect::<Option<String>>(),
+            _ if args.len() == 3 => {
+                match (string(&args[0]), string(&args[1]), string(&args[2])) {
+                    (Some(s), Some(from), Some(to)) => Some(s.replace(from.as_str(), &to)),
+                    _ => None,
+                }
+            },
+            _ => None,
+        };
+        Ok(result.map_or(Literal::Keyword(name.to_string()), Literal::String))
+    }
+
+    /// A literal with an optional PostgreSQL cast, e.g. `'2016-01-01'::date`.
+    fn value(&mut self) -> Result<Literal, Error> {
+        let l = self.literal()?;
+        while self.accept_symbol(':')? {
+            self.expect_symbol(':')?;
+            self.name()?;
+            if self.accept_symbol('(')? {
+                while !matches!(self.expect_next()?, Token::Symbol(')')) {}
+            }
+        }
+        Ok(l)
+    }
+
+    /// `SET standard_conforming_strings = on|off` says whether backslashes in
+    /// the strings which follow are literal.
+    fn set(&mut self) -> Result<Statement, Error> {
+        if self.accept_word("standard_conforming_strings")? {
+            if !self.accept_symbol('=')? {
+                self.accept_word("TO")?;
+            }
+            if let Some(Token::Word(v)) | Some(Token::Str(v)) = self.peek()?.cloned() {
+                self.lexer.backslash_escapes = v.eq_ignore_ascii_case("off") || v.eq_ignore_ascii_case("false");
+            }
+        }
+        self.skip_statement()?;
+        Ok(Statement::Other)
+    }
+
+    /// `PRAGMA` or `BEGIN TRANSACTION`, as sqlite3 writes but MySQL does not.
+    fn begin(&mut self, word: &str) -> Result<Statement, Error> {
+        if self.detect && (word.eq_ignore_ascii_case("PRAGMA") || self.accept_word("TRANSACTION")?) {
+            self.lexer.backslash_escapes = false;
+        }
+        self.skip_statement()?;
+        Ok(Statement::Other)
+    }
+
+    /// `COPY table (columns) FROM stdin;` starts a block of rows, one per line.
+    fn copy(&mut self) -> Result<Statement, Error> {
+        let table = self.name()?;
+        let mut columns = Vec::new();
+        if self.accept_symbol('(')? {
+            loop {
+                columns.push(self.identifier()?);
+                if !self.accept_symbol(',')? {
+                    break;
+                }
+            }
+            self.expect_symbol(')')?;
+        }
+        let stdin = self.accept_word("FROM")? && self.accept_word("STDIN")?;
+        self.skip_statement()?;
+        if stdin {
+            // The data starts on the line after the statement.
+            self.lexer.read_line();
+            self.copy = Some((table, columns));
+        }
+        Ok(Statement::Other)
+    }
+
+    /// The next row of a COPY block, or None after its `\.` terminator.
+    fn copy_data(&mut self) -> Option<Result<Statement, Error>> {
+        match self.lexer.read_line() {
+            Some(ref line) if line == "\\." => {
+                self.copy = None;
+                None
+            },
+            Some(line) => {
+                let (table, columns) = self.copy.clone()?;
+                let line_number = self.lexer.line() - 1;
+                Some(Ok(Statement::Insert(Insert { table, columns, rows: vec![copy_row(&line)], line: line_number })))
+            },
+            None => {
+                self.copy = None;
+                Some(Err(self.error("Unterminated COPY data")))
+            },
+        }
+    }
+
+    fn insert(&mut self) -> Result<Statement, Error> {
+        let line = self.lexer.line();
+        self.accept_word("IGNORE")?;
+        if !self.accept_word("INTO")? {
+            self.skip_statement()?;
+            return Ok(Statement::Other);
+        }
+        let table = self.name()?;
+
+        let mut columns = Vec::new();
+        if self.accept_symbol('(')? {
+            loop {
+                columns.push(self.identifier()?);
+                if !self.accept_symbol(',')? {
+                    break;
+                }
+            }
+            self.expect_symbol(')')?;
+        }
+
+        if !(self.accept_word("VALUES")? || self.accept_word("VALUE")?) {
+            // INSERT ... SELECT and INSERT ... SET are not supported.
+            self.skip_statement()?;
+            return Ok(Statement::Other);
+        }
+
+        let mut rows = Vec::new();
+        loop {
+            self.expect_symbol('(')?;
+            let mut row = Vec::new();
+            if !self.accept_symbol(')')? {
+                loop {
+                    row.push(self.value()?);
+                    if !self.accept_symbol(',')? {
+                        break;
+                    }
+                }
+                self.expect_symbol(')')?;
+            }
+            rows.push(row);
+            if !self.accept_symbol(',')? {
+                break;
+            }
+        }
+        self.skip_statement()?;
+        Ok(Statement::Insert(Insert { table, columns, rows, line }))
+    }
+
+    /// Skip the rest of a statement which could not be parsed, so that
+    /// reading can carry on with the next.
+    fn recover(&mut self, error: Error) -> Error {
+        if !self.ended {
+            // An error here is in the same statement, so it is not reported.
+            let _ = self.skip_statement();
+        }
+        let text = self.text().as_bytes().to_vec();
+        error.in_record(&text)
+    }
+
+    pub fn next_statement(&mut self) -> Option<Result<Statement, Error>> {
+        if self.copy.is_some() {
+            if let Some(row) = self.copy_data() {
+                return Some(row);
+            }
+        }
+        self.lexer.mark();
+        self.ended = false;
+        let first = match self.advance() {
+            Ok(Some(t)) => t,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(self.recover(e))),
+        };
+        let statement = match first {
+            Token::Symbol(';') => Ok(Statement::Other),
+            Token::Word(ref w) if w.eq_ignore_ascii_case("CREATE") => self.create_table(),
+            Token::Word(ref w) if w.eq_ignore_ascii_case("INSERT") => self.insert(),
+            Token::Word(ref w) if w.eq_ignore_ascii_case("COPY") => self.copy(),
+            Token::Word(ref w) if w.eq_ignore_ascii_case("ALTER") => self.alter_table(),
+            Token::Word(ref w) if w.eq_ignore_ascii_case("SET") => self.set(),
+            Token::Word(ref w) if w.eq_ignore_ascii_case("PRAGMA") || w.eq_ignore_ascii_case("BEGIN") => self.begin(w),
+            _ => self.skip_statement().map(|_| Statement::Other),
+        };
+        Some(statement.map_err(|e| self.recover(e)))
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for Parser<I> {
+    type Item = Result<Statement, Error>;
+
+    fn next(&mut self) -> Option<Result<Statement, Error>> {
+        self.next_statement()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statements(sql: &str, dialect: Option<Dialect>) -> Vec<Statement> {
+        Parser::new(sql.chars(), dialect).map(|s| s.unwrap()).collect()
+    }
+
+    fn rows(sql: &str, dialect: Option<Dialect>) -> Vec<Vec<Literal>> {
+        statements(sql, dialect).into_iter()
+            .filter_map(|s| match s {
+                Statement::Insert(i) => Some(i.rows),
+                _ => None,
+            })
+            .flat_map(|r| r.into_iter())
+            .collect()
+    }
+
+    fn string(s: &str) -> Literal {
+        Literal::String(s.to_string())
+    }
+
+    #[test]
+    fn literals() {
+        let sql = "INSERT INTO t VALUES (NULL, TRUE, -1.5, 0xCAFE, X'00ff', CURRENT_TIMESTAMP);";
+        assert_eq!(rows(sql, None), vec![vec![Literal::Null, Literal::Bool(true), Literal::Number("-1.5".to_string()),
+            Literal::Hex("CAFE".to_string()), Literal::Hex("00ff".to_string()),
+            Literal::Keyword("CURRENT_TIMESTAMP".to_string())]]);
+    }
+
+    #[test]
+    fn create_table_and_insert() {
+        let sql = "CREATE TABLE `t` (\n`id` int(11) NOT NULL,\n`name` varchar(30) DEFAULT 'x',\nPRIMARY KEY (`id`)\n);\n\
+            SET NAMES utf8;\nINSERT INTO `t` (`name`, `id`) VALUES ('a', 1), ('b', 2);";
+        let statements = statements(sql, None);
+        assert_eq!(statements.len(), 3);
+        match statements[0] {
+            Statement::CreateTable(ref t) => {
+                assert_eq!(t.name, "t");
+                let names: Vec<&str> = t.columns.iter().map(|c| c.name.as_str()).collect();
+                assert_eq!(names, vec!["id", "name"]);
+                assert_eq!(t.columns[0].sql_type, "INT(11)");
+                assert_eq!(t.columns[1].default, Some(string("x")));
+            },
+            ref s => panic!("{:?}", s),
+        }
+        assert_eq!(statements[1], Statement::Other);
+        match statements[2] {
+            Statement::Insert(ref i) => {
+                assert_eq!(i.table, "t");
+                assert_eq!(i.columns, vec!["name", "id"]);
+                assert_eq!(i.rows, vec![vec![string("a"), Literal::Number("1".to_string())],
+                    vec![string("b"), Literal::Number("2".to_string())]]);
+                assert_eq!(i.line, 7);
+            },
+            ref s => panic!("{:?}", s),
+        }
+    }
+
+    #[test]
+    fn errors_skip_to_the_next_statement() {
+        let sql = "INSERT INTO t VALUES (1 2);\nINSERT INTO t VALUES (3);";
+        let statements: Vec<Result<Statement, Error>> = Parser::new(sql.chars(), None).collect();
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].is_err());
+        assert!(statements[1].is_ok());
+    }
+
+    #[test]
+    fn strings_per_dialect() {
+        let sql = r"INSERT INTO t VALUES ('a\nb');";
+        assert_eq!(rows(sql, None), vec![vec![string("a\nb")]]);
+        assert_eq!(rows(sql, Some(Dialect::MySql)), vec![vec![string("a\nb")]]);
+        assert_eq!(rows(sql, Some(Dialect::PostgreSql)), vec![vec![string(r"a\nb")]]);
+        assert_eq!(rows(sql, Some(Dialect::Sqlite)), vec![vec![string(r"a\nb")]]);
+    }
+
+    #[test]
+    fn standard_conforming_strings() {
+        let sql = "SET standard_conforming_strings = on;\nINSERT INTO t VALUES ('C:\\new\\dir\\');\n\
+            SET standard_conforming_strings = off;\nINSERT INTO t VALUES ('a\\tb');";
+        assert_eq!(rows(sql, None), vec![vec![string(r"C:\new\dir\")], vec![string("a\tb")]]);
+    }
+
+    #[test]
+    fn sqlite_dumps_are_detected() {
+        let sql = "PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\nINSERT INTO t VALUES ('C:\\dir\\');\nCOMMIT;";
+        assert_eq!(rows(sql, None), vec![vec![string(r"C:\dir\")]]);
+        let sql = "BEGIN TRANSACTION;\nINSERT INTO t VALUES ('a\\b');";
+        assert_eq!(rows(sql, Some(Dialect::MySql)), vec![vec![string("ab")]]);
+    }
+
+    #[test]
+    fn copy_rows() {
+        assert_eq!(copy_row("1\tAnn\t\\N"), vec![string("1"), string("Ann"), Literal::Null]);
+        assert_eq!(copy_row(r"a\tb\nc\\d"), vec![string("a\tb\nc\\d")]);
+        assert_eq!(copy_row(r"\101\x42\b"), vec![string("AB\x08")]);
+        assert_eq!(copy_row(""), vec![string("")]);
+        assert_eq!(copy_row(r"\\N"), vec![string(r"\N")]);
+    }
+
+    #[test]
+    fn copy_blocks() {
+        let sql = "COPY public.t (id, name) FROM stdin;\n1\tC:\\\\dir\n2\t\\N\n\\.\nINSERT INTO t VALUES (3, 'x');";
+        let inserts: Vec<Insert> = Parser::new(sql.chars(), Some(Dialect::PostgreSql))
+            .filter_map(|s| match s.unwrap() {
+                Statement::Insert(i) => Some(i),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(inserts.len(), 3);
+        assert_eq!(inserts[0].table, "t");
+        assert_eq!(inserts[0].columns, vec!["id", "name"]);
+        assert_eq!(inserts[0].rows, vec![vec![string("1"), string(r"C:\dir")]]);
+        assert_eq!(inserts[1].rows, vec![vec![string("2"), Literal::Null]]);
+        assert_eq!(inserts[2].rows, vec![vec![Literal::Number("3".to_string()), string("x")]]);
+    }
+
+    #[test]
+    fn unterminated_copy() {
+        let sql = "COPY t (id) FROM stdin;\n1\n";
+        let statements: Vec<Result<Statement, Error>> = Parser::new(sql.chars(), None).collect();
+        assert!(statements.last().unwrap().is_err());
+    }
+
+    #[test]
+    fn bit_literals() {
+        let sql = "CREATE TABLE `t` (\n`on` bit(1) DEFAULT b'0',\n`flags` bit(8)\n);\n\
+            INSERT INTO `t` VALUES (b'1',B'101'),(0b1,b''),(_binary b'0',0B11111111);";
+        let statements = statements(sql, None);
+        match statements[0] {
+            Statement::CreateTable(ref t) => assert_eq!(t.columns[0].default, Some(Literal::Number("0".to_string()))),
+            ref s => panic!("{:?}", s),
+        }
+        let number = |n: &str| Literal::Number(n.to_string());
+        assert_eq!(rows(sql, None), vec![vec![number("1"), number("5")], vec![number("1"), number("0")],
+            vec![number("0"), number("255")]]);
+        let result: Vec<Result<Statement, Error>> = Parser::new("INSERT INTO t VALUES (b'12');".chars(), None).collect();
+        assert!(result[0].is_err());
+    }
+}
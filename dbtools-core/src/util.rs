@@ -0,0 +1,381 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+use error::Error;
+use std::cell::RefCell;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use std::process;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn digits(s: &str) -> Option<i64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse::<i64>().ok()
+}
+
+/// Parse `YYYY-MM-DD[(T| )HH:MM:SS[.fff]][Z|+hh|+hhmm|+hh:mm]` into a UTC timestamp.
+pub fn parse_date(v: &str) -> Option<NaiveDateTime> {
+    let v = v.trim();
+    if v.len() < 10 || !v.is_char_boundary(10) {
+        return None;
+    }
+    let (d, rest) = v.split_at(10);
+    let date = match NaiveDate::parse_from_str(d, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => return None,
+    };
+    if rest.is_empty() {
+        return Some(date.and_hms(0, 0, 0));
+    }
+
+    if !(rest.starts_with('T') || rest.starts_with(' ')) || rest.len() < 9 || !rest.is_char_boundary(9) {
+        return None;
+    }
+    let (t, mut rest) = rest[1..].split_at(8);
+    let time = match NaiveTime::parse_from_str(t, "%H:%M:%S") {
+        Ok(time) => time,
+        Err(_) => return None,
+    };
+    let mut dt = NaiveDateTime::new(date, time);
+
+    if rest.starts_with('.') {
+        let end = rest[1..].find(|c: char| !c.is_ascii_digit()).map_or(rest.len(), |i| i + 1);
+        let mut frac = rest[1..end].to_string();
+        frac.truncate(9);
+        while frac.len() < 9 {
+            frac.push('0');
+        }
+        dt = dt + Duration::nanoseconds(digits(&frac)?);
+        rest = &rest[end..];
+    }
+
+    let zone = rest.trim();
+    if zone.is_empty() || zone == "Z" {
+        return Some(dt);
+    }
+    let sign = match zone.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return None,
+    };
+    let mut zone = zone[1..].replace(":", "");
+    if zone.len() == 2 {
+        zone.push_str("00");
+    }
+    if zone.len() != 4 || !zone.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let offset = digits(&zone[0..2])? * 60 + digits(&zone[2..4])?;
+    Some(dt - Duration::minutes(sign * offset))
+}
+
+pub fn is_date(v: &str) -> bool {
+    parse_date(v).is_some()
+}
+
+/// Format a timestamp as MongoDB does, with a `Z` or `+0000` timezone.
+pub fn format_timestamp(d: &NaiveDateTime, tz: bool) -> String {
+    let p = d.format("%Y-%m-%dT%H:%M:%S%.3f").to_string();
+    if tz {
+        format!("{}Z", p)
+    }
+    else {
+        format!("{}+0000", p)
+    }
+}
+
+pub fn format_sql_timestamp(d: &NaiveDateTime) -> String {
+    d.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// The characters of a buffered reader, decoded a line at a time so that
+/// only one line is held in memory. Invalid UTF-8 is replaced; a read error
+/// ends the characters and is kept for `error()`.
+pub struct Chars<R: BufRead> {
+    reader: R,
+    line: String,
+    pos: usize,
+    error: Rc<RefCell<Option<io::Error>>>,
+}
+
+impl<R: BufRead> Chars<R> {
+    pub fn new(reader: R) -> Chars<R> {
+        Chars {
+            reader,
+            line: String::new(),
+            pos: 0,
+            error: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Where a read error is put, to be checked once the characters end.
+    pub fn error(&self) -> Rc<RefCell<Option<io::Error>>> {
+        self.error.clone()
+    }
+}
+
+impl<R: BufRead> Iterator for Chars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.pos >= self.line.len() {
+            let mut bytes = Vec::new();
+            match self.reader.read_until(b'\n', &mut bytes) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(e) => {
+                    *self.error.borrow_mut() = Some(e);
+                    return None;
+                },
+            }
+            self.line = String::from_utf8_lossy(&bytes).into_owned();
+            self.pos = 0;
+        }
+        let c = self.line[self.pos..].chars().next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+}
+
+/// Match a name against a pattern where `*` stands for any run of characters
+/// and `?` for any one character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut i, mut j) = (0, 0);
+    // Position after the last '*' and the name position it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+    while j < n.len() {
+        if i < p.len() && (p[i] == '?' || p[i] == n[j]) {
+            i += 1;
+            j += 1;
+        }
+        else if i < p.len() && p[i] == '*' {
+            star = Some((i + 1, j));
+            i += 1;
+        }
+        else if let Some((si, sj)) = star {
+            i = si;
+            j = sj + 1;
+            star = Some((si, sj + 1));
+        }
+        else {
+            return false;
+        }
+    }
+    p[i..].iter().all(|c| *c == '*')
+}
+
+/// True if a file name is `-`, for standard input or output.
+pub fn is_stdio(name: &str) -> bool {
+    name == "-"
+}
+
+/// A file to read, or standard input if named `-`. Standard input is read as
+/// it arrives by conversions which read their input once, and otherwise
+/// first copied to a temporary file.
+pub struct Input {
+    name: String,
+    path: String,
+    temporary: bool,
+    /// Standard input, read as it arrives.
+    stdin: bool,
+    /// Bytes read from the start of standard input, which are read again.
+    start: RefCell<Vec<u8>>,
+}
+
+impl Input {
+    /// Open a file, or standard input, which is copied to a new temporary
+    /// file so that it can be read more than once.
+    pub fn open(name: &str) -> Result<Input, Error> {
+        if !is_stdio(name) {
+            return Ok(Input::file_input(name));
+        }
+        let (path, mut f) = temporary_file()?;
+        let input = Input { name: "stdin".to_string(), path, temporary: true, stdin: false, start: RefCell::default() };
+        let stdin = io::stdin();
+        io::copy(&mut stdin.lock(), &mut f).map_err(|e| Error::io("stdin", e))?;
+        Ok(input)
+    }
+
+    /// Open a file, or standard input to be read once, as it arrives.
+    pub fn stream(name: &str) -> Input {
+        if !is_stdio(name) {
+            return Input::file_input(name);
+        }
+        Input { name: "stdin".to_string(), path: name.to_string(), temporary: false, stdin: true, start: RefCell::default() }
+    }
+
+    fn file_input(name: &str) -> Input {
+        Input { name: name.to_string(), path: name.to_string(), temporary: false, stdin: false, start: RefCell::default() }
+    }
+
+    /// Name for messages: the file name, or "stdin".
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the input is standard input, however it is read.
+    pub fn is_stdin(&self) -> bool {
+        self.stdin || self.temporary
+    }
+
+    /// Path of a file holding the input, or `-` for standard input read as
+    /// it arrives.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn file(&self) -> Result<File, Error> {
+        File::open(&self.path).map_err(|e| Error::io(&self.name, e))
+    }
+
+    /// A reader of the input from its start. Standard input read as it
+    /// arrives has only the one.
+    pub fn reader(&self) -> Result<Box<dyn Read>, Error> {
+        if self.stdin {
+            let start = self.start.replace(Vec::new());
+            return Ok(Box::new(io::Cursor::new(start).chain(io::stdin())));
+        }
+        Ok(Box::new(self.file()?))
+    }
+
+    /// Up to `len` bytes from the start of the input, e.g. to tell its
+    /// format, which its reader still reads.
+    pub fn start(&self, len: usize) -> Result<Vec<u8>, Error> {
+        let mut start = Vec::new();
+        if self.stdin {
+            let mut buffered = self.start.borrow_mut();
+            if buffered.len() < len {
+                let missing = (len - buffered.len()) as u64;
+                io::stdin().take(missing).read_to_end(&mut buffered).map_err(|e| Error::io(&self.name, e))?;
+            }
+            start.extend(buffered.iter().take(len));
+            return Ok(start);
+        }
+        self.file()?.take(len as u64).read_to_end(&mut start).map_err(|e| Error::io(&self.name, e))?;
+        Ok(start)
+    }
+}
+
+/// Create a new temporary file, which no other file or link may already be.
+fn temporary_file() -> Result<(String, File), Error> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let mut attempt = 0;
+    loop {
+        let path = env::temp_dir().join(format!("dbtools-{}-{}-{}.stdin", process::id(), nanos, attempt));
+        let path = path.to_string_lossy().into_owned();
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(f) => return Ok((path, f)),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(Error::io(&path, e)),
+        }
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Create an output file, or write to standard output if it is named `-`.
+pub fn create(output: &str) -> Result<Box<dyn Write>, Error> {
+    if is_stdio(output) {
+        return Ok(Box::new(io::stdout()));
+    }
+    let f = File::create(output).map_err(|e| Error::io(output, e))?;
+    Ok(Box::new(f))
+}
+
+/// True if an output names a directory: one which exists or ends in a slash.
+pub fn is_directory(output: &str) -> bool {
+    output.ends_with('/') || output.ends_with('\\') || Path::new(output).is_dir()
+}
+
+/// Path of the file for a table in an output directory, e.g. `out/people.csv`.
+pub fn table_file(dir: &str, table: &str, extension: &str) -> String {
+    Path::new(dir).join(format!("{}.{}", table, extension)).to_string_lossy().into_owned()
+}
+
+/// File for the records rejected from a conversion: beside the output file,
+/// e.g. `people.csv.rejects`, in the output directory, or `stdout.rejects`.
+pub fn reject_file(output: &str) -> String {
+    if is_stdio(output) {
+        return "stdout.rejects".to_string();
+    }
+    if is_directory(output) {
+        return Path::new(output).join("rejects").to_string_lossy().into_owned();
+    }
+    format!("{}.rejects", output)
+}
+
+/// Make a name usable as an SQL identifier: characters other than letters,
+/// digits and underscores become underscores, and a leading digit gets an
+/// underscore before it, e.g. `2024-users` becomes `_2024_users`.
+pub fn sanitize_identifier(name: &str) -> String {
+    let mut id: String = name.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if id.is_empty() || id.starts_with(|c: char| c.is_ascii_digit()) {
+        id.insert(0, '_');
+    }
+    id
+}
+
+/// Base name of a file without its extension, e.g. `users.v2` for
+/// `data/2024/users.v2.json`.
+pub fn file_stem(path: &str) -> String {
+    Path::new(path).file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned())
+}
+
+/// Derive a table name from an input file name: its base name without the
+/// extension, made into an identifier, e.g. `data/2024/users.v2.json`
+/// becomes `users_v2`.
+pub fn table_name(input: &str) -> String {
+    sanitize_identifier(&file_stem(input))
+}
+
+/// Check input and output files carry the extensions for their formats,
+/// e.g. `check_extensions("a.json", "JSON", "b.csv", "CSV")`. An empty
+/// format, and standard input or output, is not checked.
+pub fn check_extensions(input: &str, input_format: &str, output: &str, output_format: &str) -> Result<(), Error> {
+    let mut re = Regex::new(&format!(r".{}$", input_format.to_lowercase())).unwrap();
+    if !input_format.is_empty() && !is_stdio(input) && !re.is_match(input) {
+        return Err(Error::usage(&format!("Input file '{}' is not {}", input, input_format)));
+    }
+    re = Regex::new(&format!(r".{}$", output_format.to_lowercase())).unwrap();
+    if !output_format.is_empty() && !is_stdio(output) && !re.is_match(output) {
+        return Err(Error::usage(&format!("Output file '{}' is not {}", output, output_format)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        let d = NaiveDate::from_ymd(2020, 1, 2).and_hms(3, 4, 5);
+        assert_eq!(parse_date("2020-01-02"), Some(NaiveDate::from_ymd(2020, 1, 2).and_hms(0, 0, 0)));
+        assert_eq!(parse_date("2020-01-02T03:04:05Z"), Some(d));
+        assert_eq!(parse_date("2020-01-02 05:04:05+02:00"), Some(d));
+        assert_eq!(parse_date("2020-01-02T01:04:05-0200"), Some(d));
+        assert_eq!(parse_date("2020-01-02T03:04:05.5+00"), Some(d + Duration::milliseconds(500)));
+    }
+
+    #[test]
+    fn bad_dates() {
+        assert_eq!(parse_date("2020-13-02"), None);
+        assert_eq!(parse_date("2020-01-02T03:04"), None);
+        assert_eq!(parse_date("2020-01-02T03:04:05+0x"), None);
+        assert_eq!(parse_date("2020-01-02T03:04:05+éé"), None);
+        assert_eq!(parse_date("2020-01-02T03:04:05+é"), None);
+        assert_eq!(parse_date("2020-01-0é"), None);
+    }
+}
@@ -0,0 +1,279 @@
+use chrono::NaiveDateTime;
+use std::collections::HashSet;
+use std::fmt;
+use std::slice::Iter;
+
+/// A single typed value read from or written to any of the supported formats.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    ObjectId(String),
+    /// Timestamp normalised to UTC.
+    Date(NaiveDateTime),
+    Number(Number),
+    Bool(bool),
+    String(String),
+    Null,
+    Document(Record),
+    Array(Vec<Value>),
+    /// Binary data with its BSON subtype (4 for UUIDs).
+    Binary(u8, Vec<u8>),
+    /// Regular expression pattern and options.
+    Regex(String, String),
+    /// Internal MongoDB timestamp: seconds and increment.
+    Timestamp(u32, u32),
+    MinKey,
+    MaxKey,
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    /// True for values which map onto a single CSV cell or SQL column.
+    pub fn is_scalar(&self) -> bool {
+        !matches!(*self, Value::Document(_) | Value::Array(_))
+    }
+}
+
+/// A number kept in the form it was read, so that integers stay integers
+/// and decimals keep every digit. Numbers whose type was declared, e.g. by
+/// `$numberLong` or a BIGINT column, keep that type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+    /// Integer stored as 32 or 64 bits by its size.
+    Int(i64),
+    Int32(i32),
+    Int64(i64),
+    /// Binary floating point, e.g. a JSON number with a fraction or `$numberDouble`.
+    Double(f64),
+    /// Exact decimal text, e.g. a CSV or SQL number.
+    Decimal(String),
+    /// Exact decimal text declared as a decimal, e.g. `$numberDecimal` or a DECIMAL column.
+    Decimal128(String),
+}
+
+/// True for decimal text as a numeric literal writes it, e.g. `-1.50` or
+/// `1.23E+40`, but not `NaN`, `Infinity` or `0x1F`.
+pub fn is_decimal_text(text: &str) -> bool {
+    let text = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let mantissa = match mantissa.find('.') {
+        Some(i) => {
+            let (int, frac) = (&mantissa[..i], &mantissa[i + 1..]);
+            (int.is_empty() || digits(int)) && (frac.is_empty() || digits(frac)) && int.len() + frac.len() > 0
+        },
+        None => digits(mantissa),
+    };
+    mantissa && exponent.is_none_or(|e| digits(e.strip_prefix(['-', '+']).unwrap_or(e)))
+}
+
+impl Number {
+    /// Parse decimal text: an integer if it fits in 64 bits, otherwise the exact text.
+    pub fn parse(text: &str) -> Option<Number> {
+        let text = text.strip_prefix('+').unwrap_or(text);
+        if let Ok(n) = text.parse::<i64>() {
+            return Some(Number::Int(n));
+        }
+        if text.parse::<f64>().is_err() || !text.contains(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        // Write forms like ".5" and "5." as "0.5" and "5" so they are valid JSON.
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(d) => ("-", d),
+            None => ("", text),
+        };
+        let mut d = digits.replace(".e", "e").replace(".E", "E");
+        if d.ends_with('.') {
+            d.pop();
+        }
+        if d.starts_with('.') {
+            d.insert(0, '0');
+        }
+        Some(Number::Decimal(format!("{}{}", sign, d)))
+    }
+
+    /// True if the number is written as a numeric literal, rather than
+    /// e.g. NaN or Infinity.
+    pub fn is_literal(&self) -> bool {
+        match *self {
+            Number::Int(_) | Number::Int32(_) | Number::Int64(_) => true,
+            Number::Double(n) => n.is_finite(),
+            Number::Decimal(ref d) | Number::Decimal128(ref d) => is_decimal_text(d),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            Number::Int(n) | Number::Int64(n) => n as f64,
+            Number::Int32(n) => n as f64,
+            Number::Double(n) => n,
+            Number::Decimal(ref d) | Number::Decimal128(ref d) => d.parse::<f64>().unwrap_or(0.0),
+        }
+    }
+
+    /// True if a double holds this number exactly, i.e. it is finite and
+    /// has no more than 15 significant digits.
+    pub fn fits_double(&self) -> bool {
+        match *self {
+            Number::Int(n) | Number::Int64(n) => n.unsigned_abs() < 1 << 53,
+            Number::Int32(_) => true,
+            Number::Double(n) => n.is_finite(),
+            Number::Decimal(ref d) | Number::Decimal128(ref d) => {
+                let mantissa = d.split(['e', 'E']).next().unwrap_or("");
+                let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+                let significant = digits.trim_start_matches('0').trim_end_matches('0').len();
+                significant <= 15 && d.parse::<f64>().is_ok_and(f64::is_finite)
+            },
+        }
+    }
+
+    /// Total and fractional digits needed to hold this number exactly,
+    /// or None for numbers with an exponent or which are not finite.
+    pub fn precision(&self) -> Option<(usize, usize)> {
+        let text = match *self {
+            Number::Int(n) | Number::Int64(n) => n.unsigned_abs().to_string(),
+            Number::Int32(n) => n.unsigned_abs().to_string(),
+            Number::Decimal(ref d) | Number::Decimal128(ref d) => d.trim_start_matches('-').to_string(),
+            Number::Double(_) => return None,
+        };
+        if !text.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+            return None;
+        }
+        let (int, frac) = match text.find('.') {
+            Some(i) => (&text[..i], &text[i + 1..]),
+            None => (&text[..], ""),
+        };
+        let int = int.trim_start_matches('0');
+        Some((int.len() + frac.len(), frac.len()))
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Number::Int(n) | Number::Int64(n) => write!(f, "{}", n),
+            Number::Int32(n) => write!(f, "{}", n),
+            Number::Double(n) if n.is_nan() => write!(f, "NaN"),
+            Number::Double(n) if n.is_infinite() => write!(f, "{}Infinity", if n < 0.0 { "-" } else { "" }),
+            // Debug keeps the ".0" of whole numbers, so doubles stay doubles.
+            Number::Double(n) => write!(f, "{:?}", n),
+            Number::Decimal(ref d) | Number::Decimal128(ref d) => write!(f, "{}", d),
+        }
+    }
+}
+
+/// An ordered set of named values: a MongoDB document, a CSV row or a SQL row.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Record {
+    fields: Vec<(String, Value)>,
+}
+
+impl Record {
+    pub fn new() -> Record {
+        Record { fields: Vec::new() }
+    }
+
+    /// Set a field, replacing any existing value but keeping its position.
+    pub fn insert(&mut self, key: &str, value: Value) {
+        for f in &mut self.fields {
+            if f.0 == key {
+                f.1 = value;
+                return;
+            }
+        }
+        self.fields.push((key.to_string(), value));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.fields.iter().find(|f| f.0 == key).map(|f| &f.1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.fields.iter().map(|f| f.0.clone()).collect()
+    }
+
+    pub fn iter(&self) -> Iter<'_, (String, Value)> {
+        self.fields.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a Record {
+    type Item = &'a (String, Value);
+    type IntoIter = Iter<'a, (String, Value)>;
+
+    fn into_iter(self) -> Iter<'a, (String, Value)> {
+        self.fields.iter()
+    }
+}
+
+/// Field names of records, in the order first seen, gathered one record
+/// at a time.
+#[derive(Clone, Debug, Default)]
+pub struct FieldSet {
+    seen: HashSet<String>,
+    fields: Vec<String>,
+}
+
+impl FieldSet {
+    pub fn new() -> FieldSet {
+        FieldSet::default()
+    }
+
+    pub fn add(&mut self, r: &Record) {
+        for (k, _) in r {
+            if !self.seen.contains(k) {
+                self.seen.insert(k.clone());
+                self.fields.push(k.clone());
+            }
+        }
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    pub fn into_fields(self) -> Vec<String> {
+        self.fields
+    }
+}
+
+/// Union of the field names of all records, in the order first seen.
+pub fn union_fields<'a, I: IntoIterator<Item = &'a Record>>(records: I) -> Vec<String> {
+    let mut fields = FieldSet::new();
+    for r in records {
+        fields.add(r);
+    }
+    fields.into_fields()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_text() {
+        for text in &["0", "-1.50", "+2", ".5", "5.", "1.23E+40", "1e-7"] {
+            assert!(is_decimal_text(text), "{}", text);
+        }
+        for text in &["", ".", "-", "NaN", "Infinity", "0x1F", "1e", "1.2.3", "1 ", "1); DROP TABLE x; --"] {
+            assert!(!is_decimal_text(text), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use dbtools_core::Error;
+use std::collections::HashMap;
+
+/// A command line option, e.g. `-s|--separator <separator>`.
+#[derive(Clone, Debug)]
+pub struct Opt {
+    /// Single letter name, e.g. `s` for `-s`; empty if there is none.
+    pub short: String,
+    pub long: String,
+    /// Placeholder for the value, e.g. `<separator>`; empty for a flag.
+    pub value: String,
+    pub default: String,
+    pub required: bool,
+    pub help: String,
+}
+
+impl Opt {
+    /// An option given alone, e.g. `-l|--verbose`.
+    pub fn flag(short: &str, long: &str, help: &str) -> Opt {
+        Opt::value(short, long, "", help)
+    }
+
+    /// An option followed by its value, as `-s ;`, `--separator ;` or
+    /// `--separator=;`.
+    pub fn value(short: &str, long: &str, value: &str, help: &str) -> Opt {
+        Opt {
+            short: short.to_string(),
+            long: long.to_string(),
+            value: value.to_string(),
+            default: String::new(),
+            required: false,
+            help: help.to_string(),
+        }
+    }
+
+    pub fn default(mut self, default: &str) -> Opt {
+        self.default = default.to_string();
+        self
+    }
+
+    pub fn required(mut self) -> Opt {
+        self.required = true;
+        self
+    }
+
+    pub fn takes_value(&self) -> bool {
+        !self.value.is_empty()
+    }
+
+    /// The names of the option, e.g. `-s|--separator`.
+    pub fn names(&self) -> String {
+        if self.short.is_empty() {
+            format!("--{}", self.long)
+        }
+        else {
+            format!("-{}|--{}", self.short, self.long)
+        }
+    }
+
+    /// The names of the option with its value, e.g. `-s|--separator <separator>`.
+    pub fn synopsis(&self) -> String {
+        if self.takes_value() {
+            format!("{} {}", self.names(), self.value)
+        }
+        else {
+            self.names()
+        }
+    }
+
+    fn is_named(&self, name: &str) -> bool {
+        if let Some(long) = name.strip_prefix("--") {
+            long == self.long
+        }
+        else {
+            !self.short.is_empty() && name.strip_prefix('-') == Some(&self.short)
+        }
+    }
+}
+
+/// The options given on a command line, by long name. Options with defaults
+/// which were not given have their default values.
+#[derive(Debug, Default)]
+pub struct Args {
+    values: HashMap<String, String>,
+    given: Vec<String>,
+}
+
+impl Args {
+    /// True if a flag was given.
+    pub fn flag(&self, long: &str) -> bool {
+        self.given(long)
+    }
+
+    /// True if an option was given, rather than taking its default.
+    pub fn given(&self, long: &str) -> bool {
+        self.given.iter().any(|g| g == long)
+    }
+
+    /// Value of an option; empty if it was not given and has no default.
+    pub fn value(&self, long: &str) -> &str {
+        self.values.get(long).map_or("", |v| v)
+    }
+
+    /// The options under other names, e.g. `out-separator` as `separator`
+    /// for `("out-separator", "separator")`.
+    pub fn renamed(&self, names: &[(String, String)]) -> Args {
+        let mut args = Args { values: self.values.clone(), given: self.given.clone() };
+        for (from, to) in names.iter().filter(|n| n.0 != n.1) {
+            args.values.remove(to);
+            args.given.retain(|g| g != to);
+            if let Some(v) = self.values.get(from) {
+                args.values.insert(to.clone(), v.clone());
+            }
+            if self.given(from) {
+                args.given.push(to.clone());
+            }
+        }
+        args
+    }
+
+    /// Check every required option was given.
+    pub fn require(&self, opts: &[Opt]) -> Result<(), Error> {
+        match opts.iter().find(|o| o.required && !self.given(&o.long)) {
+            Some(o) => Err(Error::usage(&format!("Missing option {}", o.synopsis()))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse a command line, without the program name, against its options.
+/// Unknown options and stray arguments are errors.
+pub fn parse(opts: &[Opt], args: &[String]) -> Result<Args, Error> {
+    let mut parsed = Args::default();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        let (name, inline) = match arg.find('=') {
+            Some(p) if arg.starts_with('-') => (&arg[..p], Some(arg[p + 1..].to_string())),
+            _ => (&arg[..], None),
+        };
+        let opt = match opts.iter().find(|o| o.is_named(name)) {
+            Some(opt) => opt,
+            None if name.starts_with('-') && name != "-" => {
+                return Err(Error::usage(&format!("Unknown option '{}'", name)));
+            },
+            None => return Err(Error::usage(&format!("Unexpected argument '{}'", arg))),
+        };
+        let value = match inline {
+            Some(_) if !opt.takes_value() => {
+                return Err(Error::usage(&format!("Option {} takes no value", opt.names())));
+            },
+            Some(value) => value,
+            None if opt.takes_value() => {
+                i += 1;
+                match args.get(i) {
+                    Some(value) => value.clone(),
+                    None => return Err(Error::usage(&format!("Option {} needs a value", opt.synopsis()))),
+                }
+            },
+            None => String::new(),
+        };
+        parsed.values.insert(opt.long.clone(), value);
+        parsed.given.push(opt.long.clone());
+        i += 1;
+    }
+    for o in opts {
+        if !o.default.is_empty() && !parsed.values.contains_key(&o.long) {
+            parsed.values.insert(o.long.clone(), o.default.clone());
+        }
+    }
+    Ok(parsed)
+}
+
+/// Value of an option among arguments which are not yet known to be valid,
+/// e.g. `--from` before the options depending on it can be listed.
+pub fn find(args: &[String], long: &str) -> Option<String> {
+    let name = format!("--{}", long);
+    let prefix = format!("--{}=", long);
+    for (i, a) in args.iter().enumerate() {
+        if *a == name {
+            return args.get(i + 1).cloned();
+        }
+        if let Some(value) = a.strip_prefix(&prefix) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Usage line and a line of help for each option, e.g.
+/// `-s|--separator <separator>: Field separator (default: ,).`
+pub fn help(program: &str, opts: &[Opt]) -> String {
+    let required: Vec<String> = opts.iter().filter(|o| o.required).map(|o| o.synopsis()).collect();
+    let mut help = format!("Usage: {} {} [options]\n", program, required.join(" "));
+    for o in opts {
+        help.push_str(&format!("\n{}: {}", o.synopsis(), o.help));
+        if !o.default.is_empty() {
+            help.push_str(&format!(" (default: {})", o.default));
+        }
+        help.push('.');
+    }
+    help
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts() -> Vec<Opt> {
+        vec![
+            Opt::value("f", "file", "<input>", "Input").required(),
+            Opt::value("s", "separator", "<separator>", "Field separator").default(","),
+            Opt::value("", "table", "<table>", "Table"),
+            Opt::flag("l", "verbose", "Display output"),
+        ]
+    }
+
+    fn args(args: &[&str]) -> Result<Args, Error> {
+        parse(&opts(), &args.iter().map(|a| a.to_string()).collect::<Vec<String>>())
+    }
+
+    fn usage(args: Result<Args, Error>) -> String {
+        match args {
+            Err(e) => {
+                assert_eq!(e.exit_code(), 2);
+                e.to_string()
+            },
+            Ok(a) => panic!("{:?}", a),
+        }
+    }
+
+    #[test]
+    fn values_flags_and_defaults() {
+        let a = args(&["-f", "a.csv", "--table=t", "-l"]).unwrap();
+        assert_eq!(a.value("file"), "a.csv");
+        assert_eq!(a.value("table"), "t");
+        assert_eq!(a.value("separator"), ",");
+        assert!(a.flag("verbose"));
+        assert!(!a.given("separator"));
+        let a = args(&["--file", "-", "-s", ";", "--separator==", "--table", "a=b"]).unwrap();
+        assert_eq!(a.value("file"), "-");
+        assert_eq!(a.value("separator"), "=");
+        assert_eq!(a.value("table"), "a=b");
+        assert!(!a.flag("verbose"));
+        assert!(a.require(&opts()).is_ok());
+    }
+
+    #[test]
+    fn bad_options_are_usage_errors() {
+        assert_eq!(usage(args(&["-x"])), "Unknown option '-x'");
+        assert_eq!(usage(args(&["--files=a"])), "Unknown option '--files'");
+        assert_eq!(usage(args(&["a.csv"])), "Unexpected argument 'a.csv'");
+        assert_eq!(usage(args(&["-f"])), "Option -f|--file <input> needs a value");
+        assert_eq!(usage(args(&["--verbose=yes"])), "Option -l|--verbose takes no value");
+        assert_eq!(usage(args(&["-l"]).and_then(|a| a.require(&opts()).map(|_| a))),
+                   "Missing option -f|--file <input>");
+    }
+
+    #[test]
+    fn renamed_options() {
+        let a = args(&["-f", "a", "--table", "t"]).unwrap();
+        let renamed = a.renamed(&[("table".to_string(), "separator".to_string())]);
+        assert_eq!(renamed.value("separator"), "t");
+        assert!(renamed.given("separator"));
+        // An option not given leaves the other name without a value.
+        let renamed = a.renamed(&[("verbose".to_string(), "file".to_string())]);
+        assert_eq!(renamed.value("file"), "");
+        assert!(!renamed.given("file"));
+    }
+
+    #[test]
+    fn find_before_parsing() {
+        let args: Vec<String> = ["--from", "csv", "--to=sql", "-x"].iter().map(|a| a.to_string()).collect();
+        assert_eq!(find(&args, "from"), Some("csv".to_string()));
+        assert_eq!(find(&args, "to"), Some("sql".to_string()));
+        assert_eq!(find(&args, "file"), None);
+    }
+
+    #[test]
+    fn help_lists_the_options() {
+        assert_eq!(help("tool", &opts()), "Usage: tool -f|--file <input> [options]\n\
+            \n-f|--file <input>: Input.\
+            \n-s|--separator <separator>: Field separator (default: ,).\
+            \n--table <table>: Table.\
+            \n-l|--verbose: Display output.");
+    }
+}
@@ -0,0 +1,365 @@
+use cli::{Args, Opt};
+use dbtools_core::Error;
+use dbtools_core::reject::{OnError, Recover};
+use dbtools_core::util::{check_extensions, is_directory, reject_file, table_file, Input};
+use formats::{Format, Output, Reader, Table, Writer};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A conversion from one format to another, run by `dbtools convert` or by
+/// one of the original tools, e.g. `cmongo2sql` for MongoDB JSON to SQL.
+pub struct Conversion {
+    pub from: Format,
+    pub to: Format,
+    /// Name of the tool, or empty for `dbtools convert`.
+    pub tool: String,
+}
+
+impl Conversion {
+    pub fn new(from: Format, to: Format) -> Conversion {
+        Conversion { from, to, tool: String::new() }
+    }
+
+    /// The conversion of an original tool, named `c<from>2<to>`.
+    pub fn tool(name: &str) -> Option<Conversion> {
+        let mut formats = name.strip_prefix('c')?.splitn(2, '2');
+        let from = Format::parse(formats.next()?)?;
+        let to = Format::parse(formats.next()?)?;
+        let mut conversion = Conversion::new(from, to);
+        conversion.tool = format!("c{}2{}", from.name(), to.name());
+        if conversion.tool != name {
+            return None;
+        }
+        Some(conversion)
+    }
+
+    /// The command which runs the conversion.
+    pub fn program(&self) -> &str {
+        if self.tool.is_empty() { "dbtools convert" } else { &self.tool }
+    }
+
+    /// Name and version, also written in the comments of SQL dumps.
+    pub fn signature(&self) -> String {
+        let name = if self.tool.is_empty() { "dbtools" } else { &self.tool };
+        format!("{} 1.0.0 (https://github.com/stpettersens/db-tools)", name)
+    }
+
+    pub fn title(&self) -> String {
+        format!("Utility to convert a {} to a {}.", self.from.description(), self.to.description())
+    }
+
+    /// Every option of the conversion: where to read and write, how to read
+    /// the input format and write the output format, then the general ones.
+    pub fn options(&self) -> Vec<Opt> {
+        let (from, to) = (self.from, self.to);
+        let mut opts = Vec::new();
+        if self.tool.is_empty() {
+            let formats = Format::names();
+            opts.push(Opt::value("", "from", "<format>", &format!("Format to convert from: {}", formats)).required());
+            opts.push(Opt::value("", "to", "<format>", &format!("Format to convert to: {}", formats)).required());
+        }
+        let input = format!("<input.{}>", from.extension());
+        let output = format!("<output.{}>", to.extension());
+        let directory = if from == Format::Sql || from.documents() { ", or a directory for a file per table" } else { "" };
+        let file = if from.documents() { "MongoDB JSON or BSON file or mongodump directory" } else { from.file() };
+        opts.push(Opt::value("f", "file", &input, &format!("{} to convert, or - for standard input", file))
+            .required());
+        opts.push(Opt::value("o", "out", &output,
+            &format!("{} as output, or - for standard output{}", to.file(), directory)).required());
+        opts.extend(from.reader_options(to));
+        opts.extend(self.writer_options().into_iter().map(|o| o.0));
+
+        opts.push(Opt::value("e", "on-error", "<mode>", "On a bad record: fail, skip it or reject it to <output>.rejects")
+            .default("fail"));
+        opts.push(Opt::flag("i", "ignore-ext", "Ignore file extensions for input/output"));
+        opts.push(Opt::flag("l", "verbose", "Display console output on conversion"));
+        opts.push(Opt::flag("v", "version", "Display program version and exit"));
+        opts.push(Opt::flag("h", "help", "Display this help information and exit"));
+        opts
+    }
+
+    /// Options of the output format, each with the name the writer knows it
+    /// by. Converting a format to itself, e.g. CSV with another separator,
+    /// writer options named like reader options are given as `--out-<name>`.
+    fn writer_options(&self) -> Vec<(Opt, String)> {
+        let readers = self.from.reader_options(self.to);
+        self.to.writer_options(self.from).into_iter()
+            .map(|mut o| {
+                let long = o.long.clone();
+                if readers.iter().any(|r| r.long == o.long) {
+                    o.long = format!("out-{}", o.long);
+                    o.short.clear();
+                }
+                if readers.iter().any(|r| !r.short.is_empty() && r.short == o.short) {
+                    o.short.clear();
+                }
+                (o, long)
+            })
+            .collect()
+    }
+
+    /// Extension of the output files, which for MongoDB dumps depends on
+    /// whether they are BSON.
+    fn output_extension(&self, args: &Args) -> &'static str {
+        if self.to.documents() && args.flag("bson") { "bson" } else { self.to.extension() }
+    }
+
+    /// Convert the input to the output with the options given.
+    pub fn run(&self, args: &Args) -> Result<(), Error> {
+        let output = args.value("out");
+        let on_error = match OnError::parse(args.value("on-error")) {
+            Some(mode) => mode,
+            None => return Err(Error::usage(&format!("Unknown error mode '{}'", args.value("on-error")))),
+        };
+        if !args.flag("ignore-ext") {
+            let input = args.value("file");
+            let mut input_format = self.from.extension().to_uppercase();
+            // MongoDB dumps may also be BSON, or a directory as written by mongodump.
+            if self.from.documents() && input.ends_with(".bson") {
+                input_format = "BSON".to_string();
+            }
+            if self.from.documents() && Path::new(input).is_dir() {
+                input_format.clear();
+            }
+            let mut output_format = self.output_extension(args).to_uppercase();
+            if is_directory(output) {
+                output_format.clear();
+            }
+            check_extensions(input, &input_format, output, &output_format)?;
+        }
+        let verbose = args.flag("verbose");
+
+        // Standard input is read as it arrives when it is read once, and
+        // otherwise copied to a file for each pass to read.
+        let input = if self.single_pass() { Input::stream(args.value("file")) } else { Input::open(args.value("file"))? };
+        let renamed: Vec<(String, String)> = self.writer_options().into_iter().map(|(o, long)| (o.long, long)).collect();
+        let mut writer = self.to.writer(&args.renamed(&renamed), self.from, &self.signature())?;
+        let mut reader = self.from.reader(&input, args, self.to)?;
+        if self.single_pass() {
+            return self.run_once(args, &input, reader.as_mut(), writer.as_ref(), on_error);
+        }
+
+        // The tables and their columns are found on a first pass over the
+        // input, and the rows written on a second, which rejects any bad ones.
+        for row in Recover::new(reader.rows()?, on_error) {
+            let (table, r) = row?;
+            writer.add(&table, &r);
+        }
+        let tables = reader.tables();
+        let directory = check_tables(&input, &tables, output, self.to)?;
+
+        // A directory has a file per table; otherwise every table is written
+        // to the one output.
+        let mut files: Vec<(String, Vec<Table>)> = Vec::new();
+        for t in tables {
+            if !directory {
+                match files.first_mut() {
+                    Some(f) => f.1.push(t),
+                    None => files.push((output.to_string(), vec![t])),
+                }
+            }
+            else {
+                files.push((self.output_file(args, true, &t.name)?, vec![t]));
+            }
+        }
+        let mut outputs: Vec<Box<dyn Output>> = Vec::new();
+        let mut table_outputs: HashMap<String, usize> = HashMap::new();
+        for (file, tables) in &files {
+            if verbose {
+                eprintln!("Generating {}: '{}' from", self.to.file(), file);
+                for t in tables {
+                    eprintln!("table '{}' of {}: '{}'.", t.name, self.from.file(), input.name());
+                }
+                eprintln!();
+            }
+            for t in tables {
+                table_outputs.insert(t.name.clone(), outputs.len());
+            }
+            outputs.push(writer.create(tables, input.name(), file)?);
+        }
+        if verbose {
+            for line in reader.describe() {
+                eprintln!("{}", line);
+            }
+        }
+
+        let mut rows = Recover::new(reader.rows()?, on_error).reject_to(&reject_file(output));
+        for row in &mut rows {
+            let (table, r) = row?;
+            if let Some(&i) = table_outputs.get(&table) {
+                outputs[i].write(&table, &r)?;
+            }
+        }
+        for out in outputs {
+            out.finish()?;
+        }
+        if let Some(summary) = rows.summary() {
+            eprintln!("{}", summary);
+        }
+        Ok(())
+    }
+
+    /// Whether the conversion reads its input once: MongoDB output needs no
+    /// first pass, except from CSV, whose columns are typed from every row.
+    fn single_pass(&self) -> bool {
+        self.to.documents() && self.from != Format::Csv
+    }
+
+    /// Convert on a single pass over the input: the output of each table is
+    /// started at its first row, and those of tables without rows at the end.
+    fn run_once(&self, args: &Args, input: &Input, reader: &mut dyn Reader, writer: &dyn Writer,
+        on_error: OnError) -> Result<(), Error> {
+        let output = args.value("out");
+        let mut tables: Vec<Table> = Vec::new();
+        let mut outputs: Vec<Box<dyn Output>> = Vec::new();
+        let summary = {
+            let mut rows = Recover::new(reader.rows()?, on_error).reject_to(&reject_file(output));
+            for row in &mut rows {
+                let (table, r) = row?;
+                let i = match tables.iter().position(|t| t.name == table) {
+                    Some(i) => i,
+                    None => self.start_output(args, input, writer, Table::new(&table, &[]), &mut tables, &mut outputs)?,
+                };
+                outputs[i].write(&table, &r)?;
+            }
+            rows.summary()
+        };
+        for t in reader.tables() {
+            if !tables.iter().any(|opened| opened.name == t.name) {
+                self.start_output(args, input, writer, t, &mut tables, &mut outputs)?;
+            }
+        }
+        if tables.is_empty() {
+            return Err(Error::schema(input.name(), 0, "No tables to convert"));
+        }
+        if args.flag("verbose") {
+            for line in reader.describe() {
+                eprintln!("{}", line);
+            }
+        }
+        for out in outputs {
+            out.finish()?;
+        }
+        if let Some(summary) = summary {
+            eprintln!("{}", summary);
+        }
+        Ok(())
+    }
+
+    /// Start the output of a table found on a single pass, after checking
+    /// it can be written with the tables before it. Returns its position.
+    fn start_output<'w>(&self, args: &Args, input: &Input, writer: &'w dyn Writer, t: Table, tables: &mut Vec<Table>,
+        outputs: &mut Vec<Box<dyn Output + 'w>>) -> Result<usize, Error> {
+        tables.push(t);
+        let output = args.value("out");
+        let directory = check_tables(input, tables, output, self.to)?;
+        let t = &tables[tables.len() - 1];
+        let file = self.output_file(args, directory, &t.name)?;
+        if args.flag("verbose") {
+            eprintln!("Generating {}: '{}' from", self.to.file(), file);
+            eprintln!("table '{}' of {}: '{}'.", t.name, self.from.file(), input.name());
+            eprintln!();
+        }
+        outputs.push(writer.create(&tables[tables.len() - 1..], input.name(), &file)?);
+        Ok(outputs.len() - 1)
+    }
+
+    /// The output a table is written to: its own file in a directory, or
+    /// else the one output.
+    fn output_file(&self, args: &Args, directory: bool, table: &str) -> Result<String, Error> {
+        if !directory {
+            return Ok(args.value("out").to_string());
+        }
+        if table.is_empty() {
+            return Err(Error::usage("No table name specified for standard input"));
+        }
+        Ok(table_file(args.value("out"), table, self.output_extension(args)))
+    }
+}
+
+/// Check the tables read from a dump can be written to the output, making
+/// the output directory if there is one.
+fn check_tables(input: &Input, tables: &[Table], output: &str, to: Format) -> Result<bool, Error> {
+    let directory = is_directory(output);
+    if tables.is_empty() {
+        return Err(Error::schema(input.name(), 0, "No tables to convert"));
+    }
+    if tables.len() > 1 && !directory && !to.multiple_tables() {
+        let names: Vec<&str> = tables.iter().map(|t| t.name.as_str()).collect();
+        let message = format!("Dump has {} tables ({}); select one or give a directory as output",
+            tables.len(), names.join(", "));
+        return Err(Error::schema(input.name(), 0, &message));
+    }
+    if directory {
+        fs::create_dir_all(output).map_err(|e| Error::io(output, e))?;
+    }
+    Ok(directory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli;
+    use std::env;
+    use std::process;
+
+    /// Run a conversion of `text`, written to an input file in a new
+    /// temporary directory, to `out` in it, which is a directory if it ends
+    /// with a slash, and read back each of `outputs` there.
+    fn convert(conversion: &Conversion, text: &str, out: &str, options: &[&str], outputs: &[&str]) -> Vec<String> {
+        let dir = env::temp_dir().join(format!("dbtools-test-{}-{}", process::id(), outputs.join("-").replace('/', "-")));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let input = path(&format!("t.{}", conversion.from.extension()));
+        fs::write(&input, text).unwrap();
+        if out.ends_with('/') {
+            fs::create_dir(path(out)).unwrap();
+        }
+        let mut args = vec!["-f".to_string(), input, "-o".to_string(), path(out)];
+        args.extend(options.iter().map(|o| o.to_string()));
+        let parsed = cli::parse(&conversion.options(), &args).unwrap();
+        let result = conversion.run(&parsed);
+        let written: Result<Vec<String>, _> = outputs.iter().map(|o| fs::read_to_string(path(o))).collect();
+        fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        written.unwrap()
+    }
+
+    #[test]
+    fn tools() {
+        let c = Conversion::tool("cmongo2sql").unwrap();
+        assert_eq!((c.from, c.to, c.program()), (Format::Mongo, Format::Sql, "cmongo2sql"));
+        assert!(!c.options().iter().any(|o| o.long == "from"));
+        assert!(Conversion::tool("cjson2sql").is_none());
+        assert!(Conversion::tool("csql2xml").is_none());
+        let c = Conversion::new(Format::Csv, Format::Sql);
+        assert_eq!(c.program(), "dbtools convert");
+        assert!(c.options().iter().any(|o| o.long == "from" && o.required));
+    }
+
+    #[test]
+    fn passes() {
+        let pass = |from, to| Conversion::new(from, to).single_pass();
+        assert!(pass(Format::Sql, Format::Mongo));
+        assert!(pass(Format::Mongo, Format::MongoArray));
+        assert!(!pass(Format::Csv, Format::Mongo));
+        assert!(!pass(Format::Mongo, Format::Sql));
+        assert!(!pass(Format::Sql, Format::Csv));
+    }
+
+    #[test]
+    fn single_pass_writes_each_table_and_those_without_rows() {
+        let sql = "CREATE TABLE `a` (`id` int, `name` varchar(10));\nCREATE TABLE `b` (`id` int);\n\
+            INSERT INTO `a` VALUES (1,'x'),(2,NULL);\n";
+        let written = convert(&Conversion::new(Format::Sql, Format::Mongo), sql, "out/", &["-n"], &["out/a.json", "out/b.json"]);
+        assert_eq!(written, vec!["{\"id\":1,\"name\":\"x\"}\n{\"id\":2,\"name\":null}\n", ""]);
+    }
+
+    #[test]
+    fn two_passes_type_columns_from_every_row() {
+        let written = convert(&Conversion::new(Format::Csv, Format::Mongo), "id,code\n1,7\n2,x7\n", "t.json", &[], &["t.json"]);
+        assert_eq!(written, vec!["{\"id\":1,\"code\":\"7\"}\n{\"id\":2,\"code\":\"x7\"}\n"]);
+    }
+}
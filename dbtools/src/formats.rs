@@ -0,0 +1,756 @@
+use cli::{Args, Opt};
+use dbtools_core::Error;
+use dbtools_core::bson::{is_bson, BsonReader, Index, Metadata};
+use dbtools_core::csv::{single_byte, CsvDialect, CsvOutput, CsvReader, CsvWriter, Escape, Quoting};
+use dbtools_core::flatten::{ArrayMode, Flatten};
+use dbtools_core::mongo::{MongoOutput, MongoReader, MongoWriter};
+use dbtools_core::sql::{Schema, SqlIndex, SqlOutput, SqlReader, SqlTable, SqlWriter, TableFilter};
+use dbtools_core::sql::dialect::{Dialect, SqlType};
+use dbtools_core::sql::parser::{Column, Key};
+use dbtools_core::util::{is_stdio, sanitize_identifier, table_name, Input};
+use dbtools_core::value::{FieldSet, Record, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::iter;
+use std::path::Path;
+
+/// Rows read from an input, each with the name of its table.
+pub type Rows<'a> = Box<dyn Iterator<Item = Result<(String, Record), Error>> + 'a>;
+
+/// A table read from an input, with the columns it declares, e.g. the header
+/// of a CSV file or the CREATE TABLE of a SQL dump, and its keys, e.g. the
+/// indexes of a MongoDB collection.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub keys: Vec<Key>,
+}
+
+impl Table {
+    /// A table of columns known by name alone.
+    pub fn new(name: &str, columns: &[String]) -> Table {
+        let columns = columns.iter().map(|c| Column { name: c.clone(), ..Column::default() }).collect();
+        Table { name: name.to_string(), columns, keys: Vec::new() }
+    }
+}
+
+/// Reads the tables of an input, as often as a conversion needs to.
+pub trait Reader {
+    /// The rows of the input, from the first.
+    fn rows(&mut self) -> Result<Rows<'_>, Error>;
+
+    /// The tables read so far, in order.
+    fn tables(&self) -> Vec<Table>;
+
+    /// What was found out about the input, for verbose output.
+    fn describe(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Writes each table read to an output. The columns of a table come from a
+/// first pass over its rows, and the rows are written on a second.
+pub trait Writer {
+    fn add(&mut self, table: &str, r: &Record);
+
+    /// Start an output of tables: a single one, unless the format holds
+    /// several. Tables without rows have their declared columns.
+    fn create(&self, tables: &[Table], input: &str, output: &str) -> Result<Box<dyn Output + '_>, Error>;
+}
+
+/// An output being written, with the rows of its tables.
+pub trait Output {
+    fn write(&mut self, table: &str, r: &Record) -> Result<(), Error>;
+    fn finish(self: Box<Self>) -> Result<(), Error>;
+}
+
+/// A format which can be converted to and from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    /// MongoDB JSON, a document per line.
+    Mongo,
+    /// MongoDB JSON, the documents in an array.
+    MongoArray,
+    Sql,
+}
+
+/// Every format, each of which can be converted to any other, or to itself.
+pub const FORMATS: [Format; 4] = [Format::Csv, Format::Mongo, Format::MongoArray, Format::Sql];
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name.to_lowercase().as_ref() {
+            "csv" => Some(Format::Csv),
+            "mongo" | "json" => Some(Format::Mongo),
+            "mongo-array" | "json-array" => Some(Format::MongoArray),
+            "sql" => Some(Format::Sql),
+            _ => None,
+        }
+    }
+
+    /// The names of every format, e.g. for help.
+    pub fn names() -> String {
+        let names: Vec<&str> = FORMATS.iter().map(|f| f.name()).collect();
+        format!("{} or {}", names[..names.len() - 1].join(", "), names[names.len() - 1])
+    }
+
+    /// Name of the format in tool names, e.g. `mongo` in `cmongo2sql`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Mongo => "mongo",
+            Format::MongoArray => "mongo-array",
+            Format::Sql => "sql",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Format::Csv => "CSV file",
+            Format::Mongo => "MongoDB JSON dump",
+            Format::MongoArray => "MongoDB JSON array",
+            Format::Sql => "SQL dump",
+        }
+    }
+
+    pub fn file(self) -> &'static str {
+        match self {
+            Format::Csv => "CSV file",
+            Format::Mongo | Format::MongoArray => "MongoDB JSON file",
+            Format::Sql => "SQL file",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Mongo | Format::MongoArray => "json",
+            Format::Sql => "sql",
+        }
+    }
+
+    /// Records are documents, which may have sub-documents and arrays.
+    pub fn documents(self) -> bool {
+        matches!(self, Format::Mongo | Format::MongoArray)
+    }
+
+    /// A file of the format may hold several tables, e.g. a SQL dump.
+    pub fn multiple_tables(self) -> bool {
+        self == Format::Sql
+    }
+
+    /// Options for reading the format, some of which depend on the format
+    /// converted to.
+    pub fn reader_options(self, to: Format) -> Vec<Opt> {
+        let mut opts = Vec::new();
+        match self {
+            Format::Csv => {
+                opts.push(Opt::value("s", "separator", "<separator>", "Field separator").default(","));
+                opts.push(Opt::value("q", "quote", "<char>", "Quote character").default("\""));
+                opts.push(Opt::value("E", "escape", "<char>", "Character escaping quotes in quoted fields, besides doubling them"));
+                opts.push(Opt::value("c", "comment", "<char>", "Skip lines starting with this character"));
+                opts.push(Opt::flag("w", "trim", "Trim whitespace around headers and fields"));
+                opts.push(Opt::flag("H", "no-header", "The first line is a row rather than column names"));
+                opts.push(Opt::value("C", "columns", "<names>",
+                    "Comma separated column names, in place of the header line (default: column1, column2...)"));
+                if to.documents() {
+                    opts.push(Opt::value("k", "key-separator", "<separator>", "Separator for sub-document fields in headers")
+                        .default("."));
+                }
+            },
+            Format::Mongo | Format::MongoArray => {},
+            Format::Sql => {
+                opts.push(Opt::value("", "table", "<table>", "Table to convert"));
+                opts.push(Opt::value("T", "tables", "<tables>", "Comma separated tables to convert; * and ? match any characters"));
+                opts.push(Opt::value("X", "exclude-tables", "<tables>",
+                    "Comma separated tables not to convert; * and ? match any characters"));
+                opts.push(Opt::value("D", "input-dialect", "<dialect>",
+                    "SQL dialect of the input, for backslashes in strings (default: found from the dump, else mysql)"));
+                if to.documents() {
+                    opts.push(Opt::flag("O", "object-ids", "Read CHAR(24) columns of hex digits as ObjectIds"));
+                }
+            },
+        }
+        // Formats of a single table name it after the input.
+        if self != Format::Sql && to == Format::Sql {
+            opts.push(Opt::value("T", "table", "<table>",
+                "Table name to use for output (default: base name of the input file; needed for standard input)"));
+        }
+        opts
+    }
+
+    /// Options for writing the format, some of which depend on the format
+    /// converted from.
+    pub fn writer_options(self, from: Format) -> Vec<Opt> {
+        let mut opts = Vec::new();
+        match self {
+            Format::Csv => {
+                opts.push(Opt::value("s", "separator", "<separator>", "Separator to use in output").default(","));
+                opts.push(Opt::value("q", "quote", "<char>", "Quote character for output").default("\""));
+                opts.push(Opt::value("Q", "quoting", "<quoting>", "Quote minimal fields, all or non-numeric ones")
+                    .default("minimal"));
+                opts.push(Opt::value("E", "escape", "<style>", "Escape quotes by doubling them or with a backslash")
+                    .default("double"));
+                opts.push(Opt::flag("r", "crlf", "End lines with CRLF rather than LF"));
+                opts.push(Opt::flag("b", "bom", "Start output with a UTF-8 byte order mark"));
+                opts.push(Opt::flag("t", "tz", "Use \"Z\" as timezone for timestamps rather than +0000"));
+                if from.documents() {
+                    opts.push(Opt::flag("n", "no-mongo-types", "Do not use MongoDB types in output"));
+                    opts.extend(flatten_options());
+                }
+            },
+            Format::Mongo | Format::MongoArray => {
+                opts.push(Opt::flag("t", "tz", "Use \"Z\" as timezone for timestamps rather than +0000"));
+                opts.push(Opt::flag("n", "no-mongo-types", "Do not use MongoDB types in output"));
+                if self == Format::Mongo {
+                    opts.push(Opt::flag("a", "array", "Output MongoDB records as a JSON array"));
+                }
+                opts.push(Opt::flag("B", "bson", "Output BSON with a .metadata.json file, as mongodump does for mongorestore"));
+            },
+            Format::Sql => {
+                opts.push(Opt::value("d", "db", "<database>", "Database name to use for output"));
+                opts.push(Opt::value("t", "dialect", "<dialect>", "SQL dialect: mysql, postgresql, sqlite or sqlserver")
+                    .default("mysql"));
+                opts.push(Opt::flag("n", "no-comments", "Do not write comments in output"));
+                if from.documents() {
+                    opts.extend(flatten_options());
+                }
+            },
+        }
+        opts
+    }
+
+    /// A reader of the input with the options given.
+    pub fn reader<'a>(self, input: &'a Input, args: &Args, to: Format) -> Result<Box<dyn Reader + 'a>, Error> {
+        let table = match args.value("table") {
+            "" if is_stdio(args.value("file")) => String::new(),
+            "" => table_name(args.value("file")),
+            table => table.to_string(),
+        };
+        Ok(match self {
+            Format::Csv => {
+                let unflatten = if to.documents() {
+                    Some(Flatten {
+                        separator: args.value("key-separator").to_string(),
+                        ..Flatten::default()
+                    })
+                }
+                else {
+                    None
+                };
+                Box::new(CsvSource { reader: CsvReader::open(input, &csv_dialect(args)?)?, table, unflatten })
+            },
+            Format::Mongo | Format::MongoArray if Path::new(input.path()).is_dir() => {
+                if args.given("table") {
+                    return Err(Error::usage("A dump directory has a table per collection; it takes no -T|--table"));
+                }
+                Box::new(DumpSource::open(input)?)
+            },
+            Format::Mongo | Format::MongoArray => {
+                let collection = Collection::open(input, table, args.given("table"))?;
+                Box::new(MongoSource { input, collection })
+            },
+            Format::Sql => {
+                let dialect = match args.value("input-dialect") {
+                    "" => None,
+                    name => match Dialect::parse(name) {
+                        Some(d) => Some(d),
+                        None => return Err(Error::usage(&format!("Unknown SQL dialect '{}'", name))),
+                    },
+                };
+                Box::new(SqlSource {
+                    input,
+                    filter: table_filter(args)?,
+                    object_ids: args.flag("object-ids"),
+                    dialect,
+                    reader: None,
+                })
+            },
+        })
+    }
+
+    /// A writer of the format with the options given, `signature` naming the
+    /// program in SQL comments.
+    pub fn writer(self, args: &Args, from: Format, signature: &str) -> Result<Box<dyn Writer>, Error> {
+        Ok(match self {
+            Format::Csv => Box::new(CsvSink { writer: csv_writer(args, from.documents())?, columns: HashMap::new() }),
+            Format::Mongo | Format::MongoArray => {
+                Box::new(MongoSink {
+                    writer: MongoWriter {
+                        tz: args.flag("tz"),
+                        mongo_types: !args.flag("no-mongo-types"),
+                        array: self == Format::MongoArray || args.flag("array"),
+                        bson: args.flag("bson"),
+                    },
+                })
+            },
+            Format::Sql => {
+                let dialect = match Dialect::parse(args.value("dialect")) {
+                    Some(d) => d,
+                    None => return Err(Error::usage(&format!("Unknown SQL dialect '{}'", args.value("dialect")))),
+                };
+                let origin = match from {
+                    Format::Csv => "CSV file",
+                    Format::Mongo | Format::MongoArray => "MongoDB collection",
+                    Format::Sql => "SQL table",
+                };
+                Box::new(SqlSink {
+                    writer: SqlWriter {
+                        signature: signature.to_string(),
+                        origin: origin.to_string(),
+                        db: args.value("db").to_string(),
+                        comments: !args.flag("no-comments"),
+                        dialect,
+                        flatten: if from.documents() { flatten(args)? } else { Flatten::default() },
+                    },
+                    schemas: HashMap::new(),
+                })
+            },
+        })
+    }
+}
+
+fn csv_dialect(args: &Args) -> Result<CsvDialect, Error> {
+    let optional = |option: &str, value: &str| -> Result<Option<u8>, Error> {
+        if value.is_empty() { Ok(None) } else { single_byte(option, value).map(Some) }
+    };
+    Ok(CsvDialect {
+        delimiter: single_byte("Separator", args.value("separator"))?,
+        quote: single_byte("Quote", args.value("quote"))?,
+        escape: optional("Escape", args.value("escape"))?,
+        comment: optional("Comment prefix", args.value("comment"))?,
+        trim: args.flag("trim"),
+        header: !args.flag("no-header"),
+        columns: args.value("columns").split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect(),
+    })
+}
+
+/// A CSV writer; only documents are flattened and have their types
+/// annotated.
+fn csv_writer(args: &Args, documents: bool) -> Result<CsvWriter, Error> {
+    let quote = match *args.value("quote").chars().collect::<Vec<char>>() {
+        [c] => c,
+        _ => return Err(Error::usage(&format!("Quote '{}' is not a single character", args.value("quote")))),
+    };
+    let quoting = match Quoting::parse(args.value("quoting")) {
+        Some(q) => q,
+        None => return Err(Error::usage(&format!("Unknown quoting '{}'", args.value("quoting")))),
+    };
+    let escape = match Escape::parse(args.value("escape")) {
+        Some(e) => e,
+        None => return Err(Error::usage(&format!("Unknown escape style '{}'", args.value("escape")))),
+    };
+    let mut writer = CsvWriter {
+        separator: args.value("separator").to_string(),
+        quote,
+        escape,
+        quoting,
+        crlf: args.flag("crlf"),
+        bom: args.flag("bom"),
+        tz: args.flag("tz"),
+        mongo_types: documents && !args.flag("no-mongo-types"),
+        ..CsvWriter::default()
+    };
+    if documents {
+        writer.flatten = flatten(args)?;
+    }
+    Ok(writer)
+}
+
+/// Options for flattening documents into the columns of a table.
+fn flatten_options() -> Vec<Opt> {
+    vec![
+        Opt::value("k", "key-separator", "<separator>", "Separator for flattened sub-document fields").default("."),
+        Opt::value("m", "max-depth", "<depth>", "Levels of sub-documents to flatten, 0 for unlimited").default("0"),
+        Opt::value("a", "arrays", "<indexed|json>", "Write arrays as indexed columns or JSON cells").default("indexed"),
+    ]
+}
+
+fn flatten(args: &Args) -> Result<Flatten, Error> {
+    let max_depth = match args.value("max-depth").parse::<usize>() {
+        Ok(depth) => depth,
+        Err(_) => return Err(Error::usage(&format!("Invalid maximum depth '{}'", args.value("max-depth")))),
+    };
+    let arrays = match ArrayMode::parse(args.value("arrays")) {
+        Some(mode) => mode,
+        None => return Err(Error::usage(&format!("Invalid array mode '{}'", args.value("arrays")))),
+    };
+    Ok(Flatten { separator: args.value("key-separator").to_string(), max_depth, arrays })
+}
+
+fn table_filter(args: &Args) -> Result<TableFilter, Error> {
+    let (table, tables) = (args.value("table"), args.value("tables"));
+    if !table.is_empty() && !tables.is_empty() {
+        return Err(Error::usage("Select tables with either --table or -T|--tables"));
+    }
+    let include = if table.is_empty() { tables } else { table };
+    Ok(TableFilter::new(include, args.value("exclude-tables")))
+}
+
+/// A CSV file, as a single table. Headers with a key separator become
+/// sub-documents when converting to documents.
+struct CsvSource {
+    reader: CsvReader,
+    table: String,
+    unflatten: Option<Flatten>,
+}
+
+impl Reader for CsvSource {
+    fn rows(&mut self) -> Result<Rows<'_>, Error> {
+        self.reader.rewind()?;
+        let (table, unflatten) = (&self.table, &self.unflatten);
+        Ok(Box::new((&mut self.reader).map(move |r| {
+            r.map(|r| match *unflatten {
+                Some(ref f) => (table.clone(), f.unflatten(&r)),
+                None => (table.clone(), r),
+            })
+        })))
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        vec![Table::new(&self.table, self.reader.headers())]
+    }
+
+    fn describe(&self) -> Vec<String> {
+        let inference = self.reader.inference();
+        let mut lines: Vec<String> = inference.columns.iter().zip(&inference.types)
+            .map(|(c, t)| format!("Column '{}': {}", c, t))
+            .collect();
+        lines.extend(inference.widenings.iter().map(|w| w.to_string()));
+        lines
+    }
+}
+
+/// A collection of a MongoDB dump: whether it is BSON, and the table it
+/// becomes, named and indexed by the `.metadata.json` beside it, if any.
+struct Collection {
+    table: String,
+    bson: bool,
+    indexes: Vec<Index>,
+}
+
+impl Collection {
+    /// The collection of an input, whose table is named `table` if `named`,
+    /// e.g. by -T|--table, or else after its metadata. Standard input has no
+    /// metadata.
+    fn open(input: &Input, table: String, named: bool) -> Result<Collection, Error> {
+        let bson = is_bson(input)?;
+        let metadata = if input.is_stdin() {
+            Metadata::default()
+        }
+        else {
+            Metadata::read(&Metadata::file(input.path())).unwrap_or_default()
+        };
+        let table = if named || metadata.collection.is_empty() { table } else { sanitize_identifier(&metadata.collection) };
+        Ok(Collection { table, bson, indexes: metadata.indexes })
+    }
+
+    fn rows<'a>(&'a self, input: &'a Input) -> Result<Rows<'a>, Error> {
+        let table = &self.table;
+        if self.bson {
+            return Ok(Box::new(BsonReader::open(input)?.map(move |r| r.map(|r| (table.clone(), r)))));
+        }
+        Ok(Box::new(MongoReader::open(input)?.map(move |r| r.map(|r| (table.clone(), r)))))
+    }
+
+    /// The table, keyed by the indexes of the collection: the index on `_id`
+    /// is its primary key. Indexes SQL has no equivalent of, e.g. text or
+    /// 2dsphere ones, are left out.
+    fn table(&self) -> Table {
+        let mut keys = Vec::new();
+        for index in &self.indexes {
+            let columns: Option<Vec<(String, bool)>> = index.key.iter()
+                .map(|(field, direction)| match direction {
+                    Value::Number(n) => Some((field.clone(), n.to_f64() < 0.0)),
+                    _ => None,
+                })
+                .collect();
+            let primary = index.key.len() == 1 && index.key[0].0 == "_id";
+            keys.push(Key {
+                table: self.table.clone(),
+                name: index.name.clone(),
+                columns: match columns {
+                    Some(c) => c,
+                    None => continue,
+                },
+                primary,
+                unique: index.unique,
+            });
+        }
+        Table { name: self.table.clone(), columns: Vec::new(), keys }
+    }
+}
+
+/// A MongoDB JSON or BSON file, as a single table. It is opened again for
+/// each pass.
+struct MongoSource<'a> {
+    input: &'a Input,
+    collection: Collection,
+}
+
+impl<'a> Reader for MongoSource<'a> {
+    fn rows(&mut self) -> Result<Rows<'_>, Error> {
+        self.collection.rows(self.input)
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        vec![self.collection.table()]
+    }
+}
+
+/// A `mongodump` directory, with a table for each of its `.bson` or `.json`
+/// collections, in order of their file names.
+struct DumpSource {
+    collections: Vec<(Input, Collection)>,
+}
+
+impl DumpSource {
+    fn open(dump: &Input) -> Result<DumpSource, Error> {
+        let entries = fs::read_dir(dump.path()).map_err(|e| Error::io(dump.name(), e))?;
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| Error::io(dump.name(), e))?.path();
+            let name = path.to_string_lossy().into_owned();
+            if path.is_file() && (name.ends_with(".bson") || name.ends_with(".json")) && !name.ends_with(".metadata.json") {
+                files.push(name);
+            }
+        }
+        files.sort();
+        let mut collections = Vec::new();
+        for file in files {
+            let input = Input::open(&file)?;
+            let collection = Collection::open(&input, table_name(&file), false)?;
+            collections.push((input, collection));
+        }
+        Ok(DumpSource { collections })
+    }
+}
+
+impl Reader for DumpSource {
+    fn rows(&mut self) -> Result<Rows<'_>, Error> {
+        let mut rows: Rows<'_> = Box::new(iter::empty());
+        for (input, collection) in &self.collections {
+            rows = Box::new(rows.chain(collection.rows(input)?));
+        }
+        Ok(rows)
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        self.collections.iter().map(|c| c.1.table()).collect()
+    }
+}
+
+/// A SQL dump, whose tables are those read on the last pass.
+struct SqlSource<'a> {
+    input: &'a Input,
+    filter: TableFilter,
+    object_ids: bool,
+    dialect: Option<Dialect>,
+    reader: Option<SqlReader>,
+}
+
+impl<'a> Reader for SqlSource<'a> {
+    fn rows(&mut self) -> Result<Rows<'_>, Error> {
+        let reader = SqlReader::open(self.input, &self.filter, self.object_ids, self.dialect)?;
+        Ok(Box::new(self.reader.insert(reader)))
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        match self.reader {
+            Some(ref reader) => {
+                reader.tables().into_iter()
+                    .map(|t| {
+                        Table { columns: reader.columns(&t).to_vec(), keys: reader.keys(&t), name: t }
+                    })
+                    .collect()
+            },
+            None => Vec::new(),
+        }
+    }
+}
+
+struct CsvSink {
+    writer: CsvWriter,
+    columns: HashMap<String, FieldSet>,
+}
+
+impl Writer for CsvSink {
+    fn add(&mut self, table: &str, r: &Record) {
+        self.writer.add_columns(self.columns.entry(table.to_string()).or_default(), r);
+    }
+
+    fn create(&self, tables: &[Table], _: &str, output: &str) -> Result<Box<dyn Output + '_>, Error> {
+        let columns = match self.columns.get(&tables[0].name) {
+            Some(c) => c.fields().to_vec(),
+            None => tables[0].columns.iter().map(|c| c.name.clone()).collect(),
+        };
+        Ok(Box::new(self.writer.create(columns, output)?))
+    }
+}
+
+struct MongoSink {
+    writer: MongoWriter,
+}
+
+impl Writer for MongoSink {
+    fn add(&mut self, _: &str, _: &Record) {}
+
+    fn create(&self, _: &[Table], _: &str, output: &str) -> Result<Box<dyn Output + '_>, Error> {
+        Ok(Box::new(self.writer.create(output)?))
+    }
+}
+
+struct SqlSink {
+    writer: SqlWriter,
+    schemas: HashMap<String, Schema>,
+}
+
+impl Writer for SqlSink {
+    fn add(&mut self, table: &str, r: &Record) {
+        self.schemas.entry(table.to_string()).or_default().add(&self.writer.record(r));
+    }
+
+    fn create(&self, tables: &[Table], input: &str, output: &str) -> Result<Box<dyn Output + '_>, Error> {
+        let mut sql_tables = Vec::new();
+        for t in tables {
+            if t.name.is_empty() {
+                return Err(Error::usage("No table name specified for standard input"));
+            }
+            let mut columns = match self.schemas.get(&t.name) {
+                Some(s) => s.columns(),
+                None => {
+                    let mut s = Schema::new();
+                    for c in &t.columns {
+                        s.declare(&c.name);
+                    }
+                    s.columns()
+                },
+            };
+            // Declared types are kept, rather than found from the values.
+            for c in &mut columns {
+                if let Some(t) = t.columns.iter().find(|d| d.name == c.0).and_then(|d| SqlType::parse(&d.sql_type)) {
+                    c.1 = t;
+                }
+            }
+            let mut table = SqlTable::new(&t.name, columns);
+            table.not_null = t.columns.iter().filter(|c| c.not_null).map(|c| c.name.clone()).collect();
+            add_keys(&mut table, &t.keys);
+            sql_tables.push(table);
+        }
+        Ok(Box::new(self.writer.create(sql_tables, input, output)?))
+    }
+}
+
+/// Add the keys of a table which are on its columns: a primary key, and
+/// indexes. Index names are prefixed with the table's, as MySQL's are only
+/// unique within their table but other databases' within their schema. Keys
+/// on other columns, e.g. fields flattened to JSON text, are left out.
+fn add_keys(table: &mut SqlTable, keys: &[Key]) {
+    for key in keys {
+        if key.columns.is_empty() || !key.columns.iter().all(|k| table.columns.iter().any(|c| c.0 == k.0)) {
+            continue;
+        }
+        if key.primary {
+            if table.primary_key.is_empty() {
+                table.primary_key = key.columns.iter().map(|k| k.0.clone()).collect();
+            }
+            continue;
+        }
+        let name = if key.name.is_empty() {
+            let columns: Vec<&str> = key.columns.iter().map(|k| k.0.as_str()).collect();
+            sanitize_identifier(&format!("{}_{}", table.name, columns.join("_")))
+        }
+        else if key.name.starts_with(&format!("{}_", table.name)) {
+            key.name.clone()
+        }
+        else {
+            sanitize_identifier(&format!("{}_{}", table.name, key.name))
+        };
+        table.indexes.push(SqlIndex { name, columns: key.columns.clone(), unique: key.unique });
+    }
+}
+
+impl<'a> Output for CsvOutput<'a> {
+    fn write(&mut self, _: &str, r: &Record) -> Result<(), Error> {
+        CsvOutput::write(self, r)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), Error> {
+        CsvOutput::finish(*self)
+    }
+}
+
+impl<'a> Output for MongoOutput<'a> {
+    fn write(&mut self, _: &str, r: &Record) -> Result<(), Error> {
+        MongoOutput::write(self, r)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), Error> {
+        MongoOutput::finish(*self)
+    }
+}
+
+impl<'a> Output for SqlOutput<'a> {
+    fn write(&mut self, table: &str, r: &Record) -> Result<(), Error> {
+        SqlOutput::write(self, table, r)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), Error> {
+        SqlOutput::finish(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli;
+    use convert::Conversion;
+
+    #[test]
+    fn formats_by_name() {
+        for f in &FORMATS {
+            assert_eq!(Format::parse(f.name()), Some(*f));
+        }
+        assert_eq!(Format::parse("JSON"), Some(Format::Mongo));
+        assert_eq!(Format::parse("json-array"), Some(Format::MongoArray));
+        assert_eq!(Format::parse("xml"), None);
+        assert_eq!(Format::names(), "csv, mongo, mongo-array or sql");
+    }
+
+    #[test]
+    fn every_conversion_has_distinct_option_names() {
+        for &from in &FORMATS {
+            for &to in &FORMATS {
+                let opts = Conversion::new(from, to).options();
+                for (i, o) in opts.iter().enumerate() {
+                    let names = format!("{} of {} to {}", o.names(), from.name(), to.name());
+                    assert!(!opts[i + 1..].iter().any(|p| p.long == o.long), "{}", names);
+                    assert!(o.short.is_empty() || !opts[i + 1..].iter().any(|p| p.short == o.short), "{}", names);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn writer_options_named_like_reader_options() {
+        let opts = Conversion::new(Format::Csv, Format::Csv).options();
+        let separator = opts.iter().find(|o| o.long == "out-separator").unwrap();
+        assert_eq!((separator.short.as_str(), separator.default.as_str()), ("", ","));
+        assert!(opts.iter().any(|o| o.long == "separator" && o.short == "s"));
+        // A short name of a reader option is only left out of the writer's.
+        let opts = Conversion::new(Format::Csv, Format::Sql).options();
+        assert!(opts.iter().any(|o| o.long == "table" && o.short == "T"));
+    }
+
+    #[test]
+    fn unknown_dialects_are_usage_errors() {
+        let opts = Format::Sql.writer_options(Format::Csv);
+        let args = cli::parse(&opts, &["--dialect".to_string(), "oracle".to_string()]).unwrap();
+        match Format::Sql.writer(&args, Format::Csv, "") {
+            Err(e) => assert_eq!((e.exit_code(), e.to_string()), (2, "Unknown SQL dialect 'oracle'".to_string())),
+            Ok(_) => panic!("oracle"),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+/*
+dbtools
+Utility to convert between MongoDB JSON dumps, CSV files and SQL dumps.
+
+Copyright 2016 Sam Saint-Pettersen.
+Licensed under the MIT/X11 License.
+*/
+
+extern crate dbtools_core;
+
+pub mod cli;
+pub mod convert;
+pub mod formats;
+
+use convert::Conversion;
+use formats::Format;
+use dbtools_core::Error;
+use std::env;
+use std::path::Path;
+use std::process::exit;
+
+const TOOLS: [&str; 6] = ["ccsv2mongo", "ccsv2sql", "cmongo2csv", "cmongo2sql", "csql2csv", "csql2mongo"];
+
+fn display_error(conversion: Option<&Conversion>, err: &Error) -> ! {
+    eprintln!("Error: {}.", err);
+    if let Error::Usage(_) = *err {
+        display_usage(conversion, err.exit_code());
+    }
+    exit(err.exit_code());
+}
+
+/// Display the usage, on standard output when asked for with -h|--help and
+/// on standard error after an error.
+fn display_usage(conversion: Option<&Conversion>, code: i32) -> ! {
+    let mut lines = Vec::new();
+    match conversion {
+        Some(c) => {
+            lines.push(format!("\n{}", c.program()));
+            lines.push(c.title());
+        },
+        None => {
+            lines.push("\ndbtools".to_string());
+            lines.push("Utility to convert between MongoDB JSON dumps, CSV files and SQL dumps.".to_string());
+        },
+    }
+    lines.push("\nCopyright 2016 Sam Saint-Pettersen.".to_string());
+    lines.push("Licensed under the MIT/X11 License.".to_string());
+    match conversion {
+        Some(c) => lines.push(format!("\n{}", cli::help(c.program(), &c.options()))),
+        None => {
+            lines.push("\nUsage: dbtools convert --from <format> --to <format> -f|--file <input> -o|--out <output> [options]".to_string());
+            lines.push("\nFormats: csv, mongo (MongoDB JSON, written a document per line), mongo-array (MongoDB JSON, written as an array) and sql.".to_string());
+            lines.push("Any format converts to any other, or to itself, e.g. SQL to SQL of another dialect.".to_string());
+            lines.push("dbtools convert --from <format> --to <format> --help lists the options of a conversion.".to_string());
+            lines.push(format!("{} are the same as dbtools convert for their formats.", TOOLS.join(", ")));
+        },
+    }
+    for line in lines {
+        if code == 0 {
+            println!("{}", line);
+        }
+        else {
+            eprintln!("{}", line);
+        }
+    }
+    exit(code);
+}
+
+/// Run a conversion with the command line arguments after its name.
+fn run(conversion: &Conversion, args: &[String]) {
+    if args.is_empty() {
+        display_error(Some(conversion), &Error::usage("No options specified"));
+    }
+    let opts = conversion.options();
+    let parsed = cli::parse(&opts, args).unwrap_or_else(|e| display_error(Some(conversion), &e));
+    if parsed.flag("help") {
+        display_usage(Some(conversion), 0);
+    }
+    if parsed.flag("version") {
+        println!("{}", conversion.signature());
+        exit(0);
+    }
+    if let Err(e) = parsed.require(&opts).and_then(|_| conversion.run(&parsed)) {
+        display_error(Some(conversion), &e);
+    }
+}
+
+/// `dbtools convert`, with the formats given by `--from` and `--to`.
+fn convert(args: &[String]) {
+    let help = args.iter().any(|a| a == "-h" || a == "--help");
+    let format = |long: &str| match cli::find(args, long) {
+        Some(name) => match Format::parse(&name) {
+            Some(f) => Some(f),
+            None => display_error(None, &Error::usage(&format!("Unknown format '{}'", name))),
+        },
+        None if help => display_usage(None, 0),
+        None if args.is_empty() => display_error(None, &Error::usage("No options specified")),
+        None => display_error(None, &Error::usage(&format!("Missing option --{} <format>", long))),
+    };
+    let (from, to) = (format("from"), format("to"));
+    match (from, to) {
+        (Some(from), Some(to)) => {
+            run(&Conversion::new(from, to), args);
+        },
+        _ => display_usage(None, 2),
+    }
+}
+
+/// Run one of the original tools, e.g. `alias("cmongo2sql")`, which takes
+/// the options of `dbtools convert` without `--from` and `--to`.
+pub fn alias(tool: &str) {
+    match Conversion::tool(tool) {
+        Some(conversion) => run(&conversion, &env::args().skip(1).collect::<Vec<String>>()),
+        None => display_error(None, &Error::usage(&format!("Unknown tool '{}'", tool))),
+    }
+}
+
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    // Installed under the name of an original tool, e.g. as a link.
+    let program = args.first().and_then(|p| Path::new(p).file_stem()).map_or(String::new(), |p| p.to_string_lossy().into_owned());
+    if TOOLS.contains(&program.as_str()) {
+        return alias(&program);
+    }
+
+    match args.get(1).map(|a| a.as_str()) {
+        Some("convert") => convert(&args[2..]),
+        Some("-h") | Some("--help") => display_usage(None, 0),
+        Some("-v") | Some("--version") => {
+            println!("dbtools 1.0.0 (https://github.com/stpettersens/db-tools)");
+        },
+        Some(command) => display_error(None, &Error::usage(&format!("Unknown command '{}'", command))),
+        None => display_error(None, &Error::usage("No command specified")),
+    }
+}
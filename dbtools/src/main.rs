@@ -0,0 +1,13 @@
+/*
+dbtools
+Utility to convert between MongoDB JSON dumps, CSV files and SQL dumps.
+
+Copyright 2016 Sam Saint-Pettersen.
+Licensed under the MIT/X11 License.
+*/
+
+extern crate dbtools;
+
+fn main() {
+    dbtools::main();
+}